@@ -0,0 +1,145 @@
+use futures_core::Stream;
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::events::EventReceiver;
+use crate::{FrameData, FrameReceiver, VideoStreamEvent};
+
+/// `EventStream` is a `Stream` of the events of a video stream, returned by
+/// `VideoStream::events`. It ends once the stream is closed and all the events have been received.
+///
+/// It doesn't borrow the `VideoStream`, so it can be moved into a spawned task, and it doesn't
+/// depend on any async runtime. The events are shared with the `VideoStream`: each event is
+/// received by only one of them.
+pub struct EventStream {
+    receiver: Arc<EventReceiver>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: Arc<EventReceiver>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = VideoStreamEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// `FrameStream` is a `Stream` of the owned frames of a video stream, created with
+/// `FrameReceiver::into_stream`. It ends once the stream is closed and all the queued frames have
+/// been received.
+///
+/// It doesn't depend on any async runtime.
+pub struct FrameStream {
+    receiver: FrameReceiver,
+}
+
+impl FrameStream {
+    /// Hands a frame back so that its storage is reused for the next frames. See
+    /// `FrameReceiver::recycle`.
+    pub fn recycle(&self, frame: FrameData) {
+        self.receiver.recycle(frame);
+    }
+}
+
+impl FrameReceiver {
+    /// Turns the receiver into a `Stream` of frames.
+    pub fn into_stream(self) -> FrameStream {
+        FrameStream { receiver: self }
+    }
+}
+
+impl Stream for FrameStream {
+    type Item = FrameData;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::event_channel;
+    use crate::frame_queue::frame_queue;
+    use crate::{DropPolicy, EventMask};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Wake, Waker};
+    use std::thread;
+
+    /// Counts how many times the task polling a stream has been woken up.
+    #[derive(Default)]
+    struct WakeCounter(AtomicUsize);
+
+    impl Wake for WakeCounter {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn poll_next<S: Stream + Unpin>(stream: &mut S, waker: &Waker) -> Poll<Option<S::Item>> {
+        Pin::new(stream).poll_next(&mut Context::from_waker(waker))
+    }
+
+    #[test]
+    fn event_stream_can_be_moved_into_a_task() {
+        fn assert_spawnable<T: Send + 'static>(_: &T) {}
+
+        let (_sender, receiver) = event_channel(EventMask::ALL);
+        assert_spawnable(&EventStream::new(Arc::new(receiver)));
+    }
+
+    #[test]
+    fn event_stream_wakes_up_on_send_and_ends_on_close() {
+        let (sender, receiver) = event_channel(EventMask::ALL);
+        let mut events = EventStream::new(Arc::new(receiver));
+        let counter = Arc::new(WakeCounter::default());
+        let waker = Waker::from(counter.clone());
+
+        assert!(poll_next(&mut events, &waker).is_pending());
+
+        thread::spawn(move || {
+            sender.send(VideoStreamEvent::EndOfStream).unwrap();
+            sender.close();
+        })
+        .join()
+        .unwrap();
+
+        assert!(counter.0.load(Ordering::SeqCst) > 0);
+        assert!(matches!(
+            poll_next(&mut events, &waker),
+            Poll::Ready(Some(VideoStreamEvent::EndOfStream))
+        ));
+        assert!(matches!(poll_next(&mut events, &waker), Poll::Ready(None)));
+    }
+
+    #[test]
+    fn frame_stream_wakes_up_on_send_and_ends_on_close() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::DropOldest);
+        let mut frames = receiver.into_stream();
+        let counter = Arc::new(WakeCounter::default());
+        let waker = Waker::from(counter.clone());
+
+        assert!(poll_next(&mut frames, &waker).is_pending());
+
+        thread::spawn(move || {
+            assert!(sender.send_with(|frame| frame.index = 7));
+        })
+        .join()
+        .unwrap();
+
+        assert!(counter.0.load(Ordering::SeqCst) > 0);
+        assert!(matches!(
+            poll_next(&mut frames, &waker),
+            Poll::Ready(Some(frame)) if frame.index == 7
+        ));
+        // The sender has been dropped, which closes the queue
+        assert!(matches!(poll_next(&mut frames, &waker), Poll::Ready(None)));
+    }
+}
@@ -0,0 +1,313 @@
+use std::time::Duration;
+
+use crate::frame_handler::{AnyFrameHandler, Fallible, FrameHandler, FrameHandlerMut, Infallible};
+use crate::streamer::open_video_stream;
+use crate::{DextreamerError, DropPolicy, EventMask, OutputSize, PixelFormat, VideoStream};
+
+/// The shortest interval at which the position is polled, to keep the control thread from
+/// spinning.
+const MIN_POSITION_UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+/// The longest interval at which the position is polled, so that the time of the next update
+/// can always be computed.
+const MAX_POSITION_UPDATE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `AudioSink` selects where the audio of the stream is played.
+///
+/// # Variants
+///
+/// * `Auto`: Let gstreamer pick the most suitable audio output of the system.
+/// * `Disabled`: Discard the audio. The video is still played in sync with the system clock.
+/// * `Element(String)`: Use the gstreamer element with the given factory name, e.g. `"pulsesink"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AudioSink {
+    /// Let gstreamer pick the most suitable audio output of the system.
+    #[default]
+    Auto,
+    /// Discard the audio. The video is still played in sync with the system clock.
+    Disabled,
+    /// Use the gstreamer element with the given factory name, e.g. `"pulsesink"`.
+    Element(String),
+}
+
+/// Settings collected by the `VideoStreamBuilder`.
+#[derive(Debug, Clone)]
+pub(crate) struct StreamConfig {
+    pub(crate) autoplay: bool,
+    pub(crate) volume: f32,
+    /// `None` keeps the track selected by gstreamer.
+    pub(crate) audio_track: Option<Option<usize>>,
+    /// `None` keeps the track selected by gstreamer.
+    pub(crate) subtitle_track: Option<Option<usize>>,
+    /// The formats accepted for the frames, in the order of preference.
+    pub(crate) pixel_formats: Vec<PixelFormat>,
+    pub(crate) output_size: OutputSize,
+    /// Whether the frames are copied into the mailbox taken with `VideoStream::take_frame_mailbox`.
+    pub(crate) frame_mailbox: bool,
+    /// The depth and drop policy of the queue read with the `FrameReceiver`, if enabled.
+    pub(crate) frame_queue: Option<(usize, DropPolicy)>,
+    /// `None` keeps the frames on the streaming thread, without a queue.
+    pub(crate) max_queued_buffers: Option<u32>,
+    /// The depth of the queue feeding the frame handler thread. `None` runs the frame handler on
+    /// the streaming thread.
+    pub(crate) handler_queue_depth: Option<usize>,
+    pub(crate) sink_drop_policy: DropPolicy,
+    pub(crate) qos: bool,
+    pub(crate) sync: bool,
+    pub(crate) audio_sink: AudioSink,
+    pub(crate) event_mask: EventMask,
+    pub(crate) position_update_interval: Duration,
+    pub(crate) load_timeout: Duration,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            autoplay: true,
+            volume: 1.0,
+            audio_track: None,
+            subtitle_track: None,
+            pixel_formats: vec![PixelFormat::default()],
+            output_size: OutputSize::default(),
+            frame_mailbox: false,
+            frame_queue: None,
+            max_queued_buffers: None,
+            handler_queue_depth: None,
+            sink_drop_policy: DropPolicy::Block,
+            qos: false,
+            sync: true,
+            audio_sink: AudioSink::default(),
+            event_mask: EventMask::ALL,
+            position_update_interval: Duration::from_millis(250),
+            load_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// `VideoStreamBuilder` configures a video stream before opening it.
+///
+/// [`open_video`](crate::open_video) is a shortcut for a builder with the default settings.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// struct VideoFrameLoader;
+///
+/// impl dextreamer::FrameHandler for VideoFrameLoader {
+///     fn handle_new_frame(&self, frame: &dextreamer::VideoFrameRef<'_>) {
+///         println!("New frame: {}x{}", frame.width(), frame.height());
+///     }
+/// }
+///
+/// let video_stream = dextreamer::VideoStreamBuilder::new("file:///home/user/my_video.mkv")
+///     .frame_handler(VideoFrameLoader)
+///     .autoplay(false)
+///     .volume(0.5)
+///     .subtitle_track(None)
+///     .position_update_interval(Duration::from_millis(250))
+///     .open()
+///     .expect("to open the video");
+/// ```
+pub struct VideoStreamBuilder {
+    uri: String,
+    frame_handler: Option<Box<dyn AnyFrameHandler>>,
+    config: StreamConfig,
+}
+
+impl VideoStreamBuilder {
+    /// Creates a builder for the media at the given uri.
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            frame_handler: None,
+            config: StreamConfig::default(),
+        }
+    }
+
+    /// Sets the handler that receives the decoded frames. Replaces the handler set with
+    /// [`frame_handler_mut`](Self::frame_handler_mut), if any.
+    pub fn frame_handler(mut self, frame_handler: impl FrameHandler + 'static) -> Self {
+        self.frame_handler = Some(Box::new(Infallible(frame_handler)));
+        self
+    }
+
+    /// Sets a handler that receives the decoded frames and controls the stream. Replaces the
+    /// handler set with [`frame_handler`](Self::frame_handler), if any.
+    pub fn frame_handler_mut(mut self, frame_handler: impl FrameHandlerMut + 'static) -> Self {
+        self.frame_handler = Some(Box::new(Fallible(frame_handler)));
+        self
+    }
+
+    /// Sets whether the playback starts as soon as the media is loaded. Defaults to `true`.
+    ///
+    /// When set to `false`, the stream is prerolled in the paused state: the first frame is
+    /// passed to the frame handler, e.g. to show a poster frame, and `VideoStreamEvent::VideoLoaded`
+    /// reports `PlayingState::Paused`.
+    pub fn autoplay(mut self, autoplay: bool) -> Self {
+        self.config.autoplay = autoplay;
+        self
+    }
+
+    /// Sets the initial volume. The volume should be between 0.0 and 1.0. Defaults to 1.0.
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.config.volume = volume;
+        self
+    }
+
+    /// Sets the initial audio track by its ID. If `None` is provided, the audio will be disabled.
+    pub fn audio_track(mut self, audio_track: Option<usize>) -> Self {
+        self.config.audio_track = Some(audio_track);
+        self
+    }
+
+    /// Sets the initial subtitle track by its ID. If `None` is provided, the subtitles will be disabled.
+    pub fn subtitle_track(mut self, subtitle_track: Option<usize>) -> Self {
+        self.config.subtitle_track = Some(subtitle_track);
+        self
+    }
+
+    /// Sets the pixel format of the frames passed to the frame handler. Defaults to `PixelFormat::Rgba`.
+    pub fn pixel_format(self, pixel_format: PixelFormat) -> Self {
+        self.pixel_formats([pixel_format])
+    }
+
+    /// Sets the pixel formats accepted for the frames, in the order of preference. The format
+    /// that gets negotiated with the decoder is reported by `VideoFrameRef::format`.
+    ///
+    /// Accepting the format produced by the decoder, usually `PixelFormat::I420` or
+    /// `PixelFormat::Nv12`, avoids converting every frame. An empty list is ignored.
+    pub fn pixel_formats(mut self, pixel_formats: impl IntoIterator<Item = PixelFormat>) -> Self {
+        let pixel_formats: Vec<PixelFormat> = pixel_formats.into_iter().collect();
+        if !pixel_formats.is_empty() {
+            self.config.pixel_formats = pixel_formats;
+        }
+        self
+    }
+
+    /// Sets the size of the frames passed to the frame handler. Defaults to `OutputSize::Source`.
+    ///
+    /// The size can be changed later with `VideoStreamAction::SetOutputSize`. Returns
+    /// `DextreamerError::MissingElement` on open if `OutputSize::Fill` is requested without the
+    /// `videocrop` plugin from gst-plugins-good installed.
+    pub fn output_size(mut self, output_size: OutputSize) -> Self {
+        self.config.output_size = output_size;
+        self
+    }
+
+    /// Sets whether the frames are kept in a mailbox, taken with `VideoStream::take_frame_mailbox`
+    /// and polled with `FrameMailbox::latest`. Defaults to `false`.
+    ///
+    /// Unlike the frame handler, which runs on the streaming thread and slows down decoding if it
+    /// takes too long, the mailbox only holds the newest frame: frames that are not polled in time
+    /// are skipped. Every frame is copied once into memory that is reused between frames. Both
+    /// the mailbox and a frame handler can be used at the same time.
+    pub fn frame_mailbox(mut self, enabled: bool) -> Self {
+        self.config.frame_mailbox = enabled;
+        self
+    }
+
+    /// Enables the delivery of owned `FrameData` copies of the frames through a queue holding at
+    /// most `depth` frames. The receiving end is taken with `VideoStream::take_frame_receiver`.
+    ///
+    /// `drop_policy` selects what happens when a frame is decoded while the queue is full. The
+    /// storage of the frames is reused once they are passed back with `FrameReceiver::recycle`, so
+    /// steady-state playback doesn't allocate. A depth of zero is treated as one.
+    pub fn frame_queue(mut self, depth: usize, drop_policy: DropPolicy) -> Self {
+        self.config.frame_queue = Some((depth, drop_policy));
+        self
+    }
+
+    /// Queues up to `max_queued_buffers` decoded frames in front of the sink, which hands them
+    /// to the frame outputs on a thread of its own. By default there is no queue, and a slow frame
+    /// handler stalls the whole pipeline, including the audio.
+    ///
+    /// What happens when the queue is full is selected with
+    /// [`sink_drop_policy`](Self::sink_drop_policy). Zero means no limit.
+    pub fn max_queued_buffers(mut self, max_queued_buffers: u32) -> Self {
+        self.config.max_queued_buffers = Some(max_queued_buffers);
+        self
+    }
+
+    /// Runs the frame handler on a thread of its own, fed with the decoded frames through a queue
+    /// holding at most `queue_depth` frames. By default, the frame handler runs on the streaming
+    /// thread and a slow handler stalls the decoding.
+    ///
+    /// When the queue is full, the oldest frame is dropped, so a slow handler only skips frames.
+    /// The skipped frames are counted in `StreamStatistics::handler_frames_dropped`. The frames
+    /// are not copied, see `Frame`. A depth of zero is treated as one.
+    pub fn handler_thread(mut self, queue_depth: usize) -> Self {
+        self.config.handler_queue_depth = Some(queue_depth);
+        self
+    }
+
+    /// Sets what happens when a frame is decoded while the sink queue is full. Defaults to
+    /// `DropPolicy::Block`. Dropped frames are counted in `StreamStatistics::frames_dropped`.
+    ///
+    /// The policy only applies to the queue enabled with
+    /// [`max_queued_buffers`](Self::max_queued_buffers). Without it, the frames are handed to the
+    /// frame outputs on the streaming thread and never dropped by the sink.
+    pub fn sink_drop_policy(mut self, drop_policy: DropPolicy) -> Self {
+        self.config.sink_drop_policy = drop_policy;
+        self
+    }
+
+    /// Sets whether the sink drops the frames that are too late to be displayed, and asks the
+    /// decoder to skip frames when it can't keep up. Defaults to `false`.
+    pub fn qos(mut self, qos: bool) -> Self {
+        self.config.qos = qos;
+        self
+    }
+
+    /// Sets whether the frames are delivered at their presentation time, in sync with the audio.
+    /// Defaults to `true`. When disabled, the frames are delivered as fast as they are decoded.
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.config.sync = sync;
+        self
+    }
+
+    /// Sets where the audio is played. Defaults to `AudioSink::Auto`.
+    pub fn audio_sink(mut self, audio_sink: AudioSink) -> Self {
+        self.config.audio_sink = audio_sink;
+        self
+    }
+
+    /// Sets the events emitted by the stream. Defaults to `EventMask::ALL`.
+    ///
+    /// E.g. `EventMask::ALL.without(EventMask::NEW_FRAME)` stops the `VideoStreamEvent::NewFrame`
+    /// events, which are otherwise emitted for every frame. Either way, if the events are not
+    /// received fast enough, only the latest pending `VideoStreamEvent::NewFrame` and
+    /// `VideoStreamEvent::PositionChanged` events are kept.
+    pub fn event_mask(mut self, event_mask: EventMask) -> Self {
+        self.config.event_mask = event_mask;
+        self
+    }
+
+    /// Sets how often the position and the duration are polled during playback, to emit
+    /// `VideoStreamEvent::PositionChanged` and `VideoStreamEvent::DurationChanged`. Defaults to
+    /// 250 milliseconds, and can't be shorter than 10 milliseconds or longer than a day.
+    ///
+    /// The position is polled by the control thread, so the updates don't depend on the frame
+    /// rate, and are also emitted for audio-only media. While paused, the position is only
+    /// reported once after each seek.
+    pub fn position_update_interval(mut self, interval: Duration) -> Self {
+        self.config.position_update_interval =
+            interval.clamp(MIN_POSITION_UPDATE_INTERVAL, MAX_POSITION_UPDATE_INTERVAL);
+        self
+    }
+
+    /// Sets how long to wait for the media to load before giving up with
+    /// `VideoStreamEvent::LoadFailed`. Defaults to 30 seconds. `Duration::MAX` waits forever.
+    pub fn load_timeout(mut self, timeout: Duration) -> Self {
+        self.config.load_timeout = timeout;
+        self
+    }
+
+    /// Opens the stream with the collected settings.
+    ///
+    /// Errors that happen while setting up the pipeline are returned right away, errors that
+    /// happen later are delivered as `VideoStreamEvent::Error` events.
+    pub fn open(self) -> Result<VideoStream, DextreamerError> {
+        open_video_stream(self.uri, self.config, self.frame_handler)
+    }
+}
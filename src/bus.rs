@@ -0,0 +1,231 @@
+use gst::prelude::*;
+use gstreamer as gst;
+
+use gstreamer::Bus;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use crate::events::EventSender;
+use crate::streamer::InternalMessage;
+use crate::{DextreamerError, VideoStreamEvent};
+
+/// `LoadOutcome` is the result of waiting for the media to load.
+enum LoadOutcome {
+    Loaded,
+    Failed(DextreamerError),
+    /// The stream was closed before the media was loaded.
+    Shutdown,
+}
+
+/// Waits until the media has been loaded, an error is posted or `timeout` runs out. Warnings and
+/// infos posted in the meantime are forwarded to the events receiver.
+fn wait_for_video_to_load(
+    playbin_message_bus: &Bus,
+    sender: &EventSender,
+    timeout: Duration,
+) -> LoadOutcome {
+    // A timeout too long to be represented means no timeout at all
+    let deadline = Instant::now().checked_add(timeout);
+
+    // Listen for messages on the pipeline's bus until `async-done` is received, which means
+    //  that media has been loaded and playback can begin.
+    loop {
+        let remaining = deadline.and_then(|deadline| {
+            gst::ClockTime::try_from(deadline.saturating_duration_since(Instant::now())).ok()
+        });
+        let Some(msg) = playbin_message_bus.timed_pop(remaining) else {
+            return LoadOutcome::Failed(DextreamerError::LoadTimeout(timeout));
+        };
+
+        match msg.view() {
+            // The async state change has completed
+            gst::MessageView::AsyncDone(_) => return LoadOutcome::Loaded,
+            gst::MessageView::Error(err) => {
+                return LoadOutcome::Failed(pipeline_error(&msg, err));
+            }
+            gst::MessageView::Warning(..) | gst::MessageView::Info(..) => {
+                if let Some(event) = diagnostic_event(&msg) {
+                    // The receiver may be gone already, the load goes on regardless
+                    let _ = sender.send(event);
+                }
+            }
+            _ if is_shutdown_message(&msg) => return LoadOutcome::Shutdown,
+            _ => (),
+        }
+    }
+}
+
+/// Name of the application message that tells the bus thread to exit.
+pub(crate) const SHUTDOWN_MESSAGE_NAME: &str = "dextreamer-shutdown";
+
+fn is_shutdown_message(message: &gst::MessageRef) -> bool {
+    match message.view() {
+        gst::MessageView::Application(application) => application
+            .structure()
+            .is_some_and(|s| s.name() == SHUTDOWN_MESSAGE_NAME),
+        _ => false,
+    }
+}
+
+/// Returns the name of the element that posted the message, and the message with its debug details.
+fn describe_bus_message(
+    message: &gst::MessageRef,
+    error: gst::glib::Error,
+    debug: Option<gst::glib::GString>,
+) -> (Option<String>, String, Option<String>) {
+    (
+        message.src().map(|src| src.path_string().to_string()),
+        error.to_string(),
+        debug.map(|debug| debug.to_string()),
+    )
+}
+
+/// Converts a warning or info message into the matching event.
+fn diagnostic_event(message: &gst::MessageRef) -> Option<VideoStreamEvent> {
+    match message.view() {
+        gst::MessageView::Warning(warning) => {
+            let (source_element, message, debug) =
+                describe_bus_message(message, warning.error(), warning.debug());
+
+            Some(VideoStreamEvent::Warning {
+                source_element,
+                message,
+                debug,
+            })
+        }
+        gst::MessageView::Info(info) => {
+            let (source_element, message, debug) =
+                describe_bus_message(message, info.error(), info.debug());
+
+            Some(VideoStreamEvent::Info {
+                source_element,
+                message,
+                debug,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn pipeline_error(message: &gst::MessageRef, err: &gst::message::Error) -> DextreamerError {
+    let (source_element, message, debug) = describe_bus_message(message, err.error(), err.debug());
+
+    DextreamerError::Pipeline {
+        source_element,
+        message,
+        debug,
+    }
+}
+
+/// Takes the error the pipeline posted when it failed to change its state, if any.
+pub(crate) fn pop_pipeline_error(playbin_message_bus: &Bus) -> Option<DextreamerError> {
+    let msg = playbin_message_bus.pop_filtered(&[gst::MessageType::Error])?;
+
+    match msg.view() {
+        gst::MessageView::Error(err) => Some(pipeline_error(&msg, err)),
+        _ => None,
+    }
+}
+
+/// Forwards the messages posted on the pipeline's bus to the events receiver until an error
+/// happens or the stream is closed.
+fn forward_bus_messages(
+    playbin_message_bus: &Bus,
+    internal_sender: &Sender<InternalMessage>,
+    sender: &EventSender,
+) {
+    for msg in playbin_message_bus.iter_timed(gst::ClockTime::NONE) {
+        use gst::MessageView;
+
+        let (event, is_fatal) = match msg.view() {
+            MessageView::Eos(..) => (VideoStreamEvent::EndOfStream, false),
+            MessageView::Error(err) => (VideoStreamEvent::Error(pipeline_error(&msg, err)), true),
+            MessageView::Warning(..) | MessageView::Info(..) => {
+                let Some(event) = diagnostic_event(&msg) else {
+                    continue;
+                };
+                (event, false)
+            }
+            _ if is_shutdown_message(&msg) => break,
+            MessageView::AsyncDone(_) => {
+                // Only the pipeline itself is done once all of its elements are
+                let is_from_pipeline = msg.src().is_some_and(|src| src.parent().is_none());
+                if is_from_pipeline && internal_sender.send(InternalMessage::AsyncDone).is_err() {
+                    break;
+                }
+                continue;
+            }
+            MessageView::SegmentDone(_) => {
+                // Posted by the pipeline instead of `Eos` while the playback is looped
+                let is_from_pipeline = msg.src().is_some_and(|src| src.parent().is_none());
+                if is_from_pipeline && internal_sender.send(InternalMessage::SegmentDone).is_err() {
+                    break;
+                }
+                continue;
+            }
+            MessageView::DurationChanged(_) => {
+                // The new duration is queried by the control thread
+                if internal_sender
+                    .send(InternalMessage::DurationChanged)
+                    .is_err()
+                {
+                    break;
+                }
+                continue;
+            }
+            MessageView::StateChanged(_state_changed) =>
+            // We are only interested in state-changed messages from playbin
+            {
+                let is_from_pipeline = msg.src().is_some_and(|src| src.parent().is_none());
+                if is_from_pipeline
+                    && internal_sender
+                        .send(InternalMessage::PipelineStateChanged)
+                        .is_err()
+                {
+                    break;
+                }
+
+                // println!("state changed");
+                // if state_changed
+                //     .src()
+                //     .map(|s| s == &playbin_pipeline)
+                //     .unwrap_or(false)
+                //     && state_changed.current() == gst::State::Playing
+                // {
+                //     // Generate a dot graph of the pipeline to GST_DEBUG_DUMP_DOT_DIR if defined
+                //     let bin_ref = playbin_pipeline.downcast_ref::<gst::Bin>().unwrap();
+                //     bin_ref.debug_to_dot_file(gst::DebugGraphDetails::all(), "PLAYING");
+                // }
+                continue;
+            }
+
+            _ => continue,
+        };
+
+        if sender.send(event).is_err() || is_fatal {
+            break;
+        }
+    }
+}
+
+/// Waits for the media to load, notifies the control thread about it and then forwards the bus
+/// messages until the stream is closed.
+pub(crate) fn watch_bus(
+    playbin_message_bus: &Bus,
+    internal_sender: &Sender<InternalMessage>,
+    sender: &EventSender,
+    load_timeout: Duration,
+) {
+    let message = match wait_for_video_to_load(playbin_message_bus, sender, load_timeout) {
+        LoadOutcome::Loaded => InternalMessage::VideoLoaded,
+        LoadOutcome::Failed(err) => InternalMessage::LoadFailed(err),
+        LoadOutcome::Shutdown => return,
+    };
+    let is_loaded = matches!(message, InternalMessage::VideoLoaded);
+
+    if internal_sender.send(message).is_ok() && is_loaded {
+        forward_bus_messages(playbin_message_bus, internal_sender, sender);
+        // The seeks can't complete anymore without the `AsyncDone` messages
+        let _ = internal_sender.send(InternalMessage::BusStopped);
+    }
+}
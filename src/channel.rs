@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::Waker;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The state shared by both ends of a `Channel`.
+pub(crate) struct ChannelState<T, S> {
+    pub(crate) items: VecDeque<T>,
+    /// Set when the channel is closed, after which nothing is sent anymore.
+    pub(crate) closed: bool,
+    /// The state specific to the kind of channel.
+    pub(crate) extra: S,
+    /// The waker of the task polling the receiver, if any.
+    waker: Option<Waker>,
+}
+
+/// A queue shared between the threads of a stream and a receiver, which either blocks on it or
+/// polls it from an async task. The senders decide how the items are queued, see the
+/// `EventSender` and the `FrameSender`.
+pub(crate) struct Channel<T, S> {
+    state: Mutex<ChannelState<T, S>>,
+    item_sent: Condvar,
+    item_taken: Condvar,
+}
+
+impl<T, S> Channel<T, S> {
+    pub(crate) fn new(extra: S) -> Self {
+        Self {
+            state: Mutex::new(ChannelState {
+                items: VecDeque::new(),
+                closed: false,
+                extra,
+                waker: None,
+            }),
+            item_sent: Condvar::new(),
+            item_taken: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ChannelState<T, S>> {
+        // The state stays consistent even if a thread panicked while holding the lock
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues an item and wakes up the receiver.
+    pub(crate) fn push(&self, state: &mut ChannelState<T, S>, item: T) {
+        state.items.push_back(item);
+        self.notify_receiver(state);
+    }
+
+    /// Blocks until the receiver takes an item or the channel is closed.
+    pub(crate) fn wait_for_room<'a>(
+        &self,
+        state: MutexGuard<'a, ChannelState<T, S>>,
+    ) -> MutexGuard<'a, ChannelState<T, S>> {
+        self.item_taken
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Closes the channel. The receiver gets the pending items, and then `None`.
+    pub(crate) fn close(&self, state: &mut ChannelState<T, S>) {
+        state.closed = true;
+        self.notify_receiver(state);
+        self.item_taken.notify_all();
+    }
+
+    /// Wakes up the receiver, whether it blocks on the condition variable or polls from a task.
+    fn notify_receiver(&self, state: &mut ChannelState<T, S>) {
+        self.item_sent.notify_one();
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn take(&self, state: &mut ChannelState<T, S>) -> Option<T> {
+        let item = state.items.pop_front()?;
+        self.item_taken.notify_one();
+
+        Some(item)
+    }
+
+    /// Blocks until the next item. Returns `None` once the channel is closed and all the items
+    /// have been received.
+    pub(crate) fn recv(&self) -> Option<T> {
+        let mut state = self.lock();
+
+        loop {
+            if let Some(item) = self.take(&mut state) {
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+
+            state = self
+                .item_sent
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Returns the next item if there is one, without blocking.
+    pub(crate) fn try_recv(&self) -> Option<T> {
+        let mut state = self.lock();
+        self.take(&mut state)
+    }
+
+    /// Waits for the next item for at most `timeout`. A timeout too long to be represented waits
+    /// as long as `recv`.
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            return self.recv();
+        };
+        let mut state = self.lock();
+
+        loop {
+            if let Some(item) = self.take(&mut state) {
+                return Some(item);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if state.closed || remaining.is_zero() {
+                return None;
+            }
+
+            state = self
+                .item_sent
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Returns the next item if there is one, or registers the task to be woken up by the next
+    /// item otherwise.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.lock();
+
+        if let Some(item) = self.take(&mut state) {
+            return Poll::Ready(Some(item));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -0,0 +1,131 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use crate::DextreamerError;
+
+#[derive(Default)]
+struct CompletionState {
+    result: Option<Result<(), DextreamerError>>,
+    /// Set if the sender is dropped without completing, e.g. because the stream is closed.
+    is_abandoned: bool,
+    waker: Option<Waker>,
+}
+
+/// Creates a one-shot channel reporting the outcome of an operation to an async task.
+pub(crate) fn completion() -> (CompletionSender, Completion) {
+    let state = Arc::new(Mutex::new(CompletionState::default()));
+
+    (
+        CompletionSender {
+            state: state.clone(),
+        },
+        Completion { state },
+    )
+}
+
+fn lock(state: &Mutex<CompletionState>) -> MutexGuard<'_, CompletionState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) struct CompletionSender {
+    state: Arc<Mutex<CompletionState>>,
+}
+
+impl CompletionSender {
+    pub(crate) fn complete(self, result: Result<(), DextreamerError>) {
+        let mut state = lock(&self.state);
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for CompletionSender {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        if state.result.is_none() {
+            state.is_abandoned = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// `Completion` resolves once the operation it was returned for has completed, e.g. when the
+/// pipeline has reached the state requested with `VideoStream::play_async`.
+///
+/// Resolves to `DextreamerError::ChannelDisconnected` if the stream is closed before that.
+pub struct Completion {
+    state: Arc<Mutex<CompletionState>>,
+}
+
+impl Future for Completion {
+    type Output = Result<(), DextreamerError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.state);
+
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(result);
+        }
+        if state.is_abandoned {
+            return Poll::Ready(Err(DextreamerError::ChannelDisconnected));
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlayingState;
+    use std::thread;
+
+    fn poll(completion: &mut Completion) -> Poll<Result<(), DextreamerError>> {
+        let mut cx = Context::from_waker(Waker::noop());
+        Pin::new(completion).poll(&mut cx)
+    }
+
+    #[test]
+    fn pending_until_completed() {
+        let (sender, mut completion) = completion();
+
+        assert!(poll(&mut completion).is_pending());
+        sender.complete(Ok(()));
+
+        assert_eq!(poll(&mut completion), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn delivers_the_result() {
+        let (sender, mut completion) = completion();
+
+        let error = DextreamerError::StateChange(PlayingState::Playing);
+        thread::spawn(move || sender.complete(Err(error)))
+            .join()
+            .unwrap();
+
+        assert_eq!(
+            poll(&mut completion),
+            Poll::Ready(Err(DextreamerError::StateChange(PlayingState::Playing)))
+        );
+    }
+
+    #[test]
+    fn dropped_sender_disconnects() {
+        let (sender, mut completion) = completion();
+
+        drop(sender);
+
+        assert_eq!(
+            poll(&mut completion),
+            Poll::Ready(Err(DextreamerError::ChannelDisconnected))
+        );
+    }
+}
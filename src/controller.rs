@@ -0,0 +1,826 @@
+use gst::prelude::*;
+use gstreamer as gst;
+
+use gstreamer::Bus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::builder::StreamConfig;
+use crate::bus::SHUTDOWN_MESSAGE_NAME;
+#[cfg(feature = "async")]
+use crate::completion::CompletionSender;
+use crate::events::EventSender;
+use crate::playbin_query::{
+    audio_tracks, current_audio_track, current_subtitle_track, set_playbin_flag, subtitle_tracks,
+    video_duration, video_position, PLAYBIN_FLAG_AUDIO, PLAYBIN_FLAG_TEXT,
+};
+use crate::seek::MAX_SEEK_POSITION;
+#[cfg(feature = "async")]
+use crate::streamer::to_gst_state;
+use crate::streamer::{set_pipeline_state, InternalMessage};
+use crate::video_sink::VideoSinkBin;
+use crate::{
+    AudioSink, DextreamerError, LoopMode, PlayingState, SeekMode, SeekTarget, VideoInfo,
+    VideoStreamAction, VideoStreamEvent,
+};
+
+/// Converts a position to a clock time, clamped to the positions a seek can reach, as
+/// `gst::ClockTime` can't hold its largest value.
+fn seconds_to_clock_time(seconds: f64) -> gst::ClockTime {
+    let seconds = seconds.clamp(0.0, MAX_SEEK_POSITION);
+    gst::ClockTime::from_nseconds((seconds * 1_000_000_000.0) as u64)
+}
+
+/// How long a seek may take to preroll before it is reported as failed.
+const SEEK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `SeekInFlight` is a flushing seek waiting for the pipeline to preroll.
+#[derive(Debug, Clone, Copy)]
+struct SeekInFlight {
+    /// The position requested by a `VideoStreamAction::Seek`, reported once the seek completes.
+    /// `None` for the seeks that apply a rate, a loop or a step.
+    requested: Option<f64>,
+    /// The seek fails if the pipeline hasn't prerolled by then.
+    deadline: Instant,
+}
+
+impl SeekInFlight {
+    fn new(requested: Option<f64>) -> Self {
+        Self {
+            requested,
+            deadline: Instant::now() + SEEK_TIMEOUT,
+        }
+    }
+}
+
+/// `StreamController` holds the state of the control thread, which applies the actions to the
+/// pipeline and reports their outcome as events.
+pub(crate) struct StreamController {
+    playbin_pipeline: gst::Element,
+    video_sink: VideoSinkBin,
+    sender: EventSender,
+    config: StreamConfig,
+    /// Shared with the video sink, which only delivers prerolled frames while the stream is paused.
+    is_paused: Arc<AtomicBool>,
+    /// The position is only polled once the media has been loaded.
+    is_loaded: bool,
+    /// Set by a seek, so that the new position is reported even if the stream is paused.
+    is_position_update_pending: bool,
+    next_position_update: Instant,
+    last_position: Option<f64>,
+    last_duration: f64,
+    /// The playback rate, applied to every seek.
+    rate: f64,
+    /// The rate of the last seek. Differs from `rate` after stepping against the direction of
+    /// the playback, until the playback is resumed.
+    segment_rate: f64,
+    /// Every seek is a segment seek while the playback is looped.
+    loop_mode: LoopMode,
+    loop_iterations: u64,
+    /// The last flushing seek, until the pipeline has prerolled after it.
+    seek_in_flight: Option<SeekInFlight>,
+    /// The seeks sent while another one is in flight, merged into a single seek.
+    queued_seek: Option<(SeekTarget, SeekMode)>,
+    /// Cleared once the bus thread stops forwarding messages, after which no seek can complete.
+    is_bus_running: bool,
+    /// The state changes awaited by async tasks, along with the state they are waiting for.
+    #[cfg(feature = "async")]
+    pending_state_changes: Vec<(PlayingState, CompletionSender)>,
+}
+
+impl StreamController {
+    pub(crate) fn new(
+        playbin_pipeline: gst::Element,
+        video_sink: VideoSinkBin,
+        sender: EventSender,
+        config: StreamConfig,
+        is_paused: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            playbin_pipeline,
+            video_sink,
+            sender,
+            config,
+            is_paused,
+            is_loaded: false,
+            is_position_update_pending: false,
+            next_position_update: Instant::now(),
+            last_position: None,
+            last_duration: 0.0,
+            rate: 1.0,
+            segment_rate: 1.0,
+            loop_mode: LoopMode::Off,
+            loop_iterations: 0,
+            seek_in_flight: None,
+            queued_seek: None,
+            is_bus_running: true,
+            #[cfg(feature = "async")]
+            pending_state_changes: Vec::new(),
+        }
+    }
+
+    fn set_playing_state(&self, playing_state: PlayingState) -> Result<(), DextreamerError> {
+        let was_paused = self.is_paused.load(Ordering::SeqCst);
+        // Updated before the state change, as the preroll can happen before `set_state` returns
+        self.is_paused
+            .store(playing_state == PlayingState::Paused, Ordering::SeqCst);
+
+        if let Err(err) = set_pipeline_state(&self.playbin_pipeline, playing_state) {
+            self.is_paused.store(was_paused, Ordering::SeqCst);
+            return Err(err);
+        }
+
+        self.sender
+            .send(VideoStreamEvent::PlayingStateChanged(playing_state))
+    }
+
+    /// Whether the position has to be polled at `next_position_update`.
+    fn needs_position_update(&self) -> bool {
+        self.is_loaded
+            && (self.is_position_update_pending || !self.is_paused.load(Ordering::SeqCst))
+    }
+
+    /// Queries the position and the duration, and reports the ones that have changed since the
+    /// previous update.
+    fn update_position(&mut self) -> Result<(), DextreamerError> {
+        self.next_position_update = Instant::now() + self.config.position_update_interval;
+
+        // Fails while the pipeline is prerolling, in which case the update is retried later
+        let Some(position) = video_position(&self.playbin_pipeline) else {
+            return Ok(());
+        };
+        self.is_position_update_pending = false;
+
+        if self.last_position != Some(position) {
+            self.last_position = Some(position);
+            self.sender
+                .send(VideoStreamEvent::PositionChanged(position))?;
+        }
+
+        self.update_duration()
+    }
+
+    fn update_duration(&mut self) -> Result<(), DextreamerError> {
+        let duration = video_duration(&self.playbin_pipeline);
+        if duration != self.last_duration {
+            self.last_duration = duration;
+            self.sender
+                .send(VideoStreamEvent::DurationChanged(duration))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the position once the pipeline has had time to preroll after a seek, even if the
+    /// stream is paused.
+    fn schedule_position_update(&mut self) {
+        self.is_position_update_pending = true;
+        self.next_position_update = Instant::now() + self.config.position_update_interval;
+    }
+
+    /// Seeks to `position` at `rate`. When playing backwards, the media is played from
+    /// `position` down to its start.
+    fn seek(
+        &mut self,
+        rate: f64,
+        position: gst::ClockTime,
+        mode: SeekMode,
+    ) -> Result<(), gst::glib::BoolError> {
+        // A step interrupted by the seek must not report the frames delivered after it
+        self.seek_expecting_frames(rate, position, mode, 0)
+    }
+
+    /// Seeks like `seek`, and reports the position of the `frames`th frame delivered after it.
+    fn seek_expecting_frames(
+        &mut self,
+        rate: f64,
+        position: gst::ClockTime,
+        mode: SeekMode,
+        frames: u32,
+    ) -> Result<(), gst::glib::BoolError> {
+        // Set before seeking, as the preroll can be delivered before `seek` returns. Until a
+        //  frame is delivered at the new position, the position is queried instead.
+        self.video_sink.stepping().expect_frames(frames);
+        let last_frame_position = self.video_sink.stepping().forget_last_frame();
+        if let Err(err) = self.seek_with_flags(rate, position, mode.seek_flags()) {
+            let stepping = self.video_sink.stepping();
+            stepping.expect_frames(0);
+            stepping.restore_last_frame(last_frame_position);
+            return Err(err);
+        }
+        // Supersedes the seek in flight, if any, while still moving to the position it requested,
+        //  see `current_position`
+        self.seek_in_flight = Some(SeekInFlight::new(self.requested_position()));
+
+        Ok(())
+    }
+
+    /// Seeks with the given flags, only playing the looped part of the media if any.
+    fn seek_with_flags(
+        &mut self,
+        rate: f64,
+        position: gst::ClockTime,
+        mut flags: gst::SeekFlags,
+    ) -> Result<(), gst::glib::BoolError> {
+        let (start, stop) = match self.loop_mode.bounds(self.duration()) {
+            Some((start, stop)) => {
+                // The pipeline posts `SegmentDone` instead of `Eos` at the end of the segment
+                flags |= gst::SeekFlags::SEGMENT;
+                (
+                    seconds_to_clock_time(start),
+                    stop.map(seconds_to_clock_time),
+                )
+            }
+            None => (gst::ClockTime::ZERO, None),
+        };
+        let position = match stop {
+            Some(stop) => position.clamp(start, stop),
+            None => position.max(start),
+        };
+        self.segment_rate = rate;
+
+        if rate > 0.0 {
+            let stop_type = if stop.is_some() {
+                gst::SeekType::Set
+            } else {
+                gst::SeekType::None
+            };
+            self.playbin_pipeline
+                .seek(rate, flags, gst::SeekType::Set, position, stop_type, stop)
+        } else {
+            self.playbin_pipeline.seek(
+                rate,
+                flags,
+                gst::SeekType::Set,
+                start,
+                gst::SeekType::Set,
+                position,
+            )
+        }
+    }
+
+    /// Changes the loop mode. A range loop starts at the beginning of the range, while the
+    /// other modes keep the current position.
+    fn set_loop(&mut self, loop_mode: LoopMode) -> Result<(), DextreamerError> {
+        // The range can only be checked against the media once it is loaded
+        let duration = if self.is_loaded { self.duration() } else { 0.0 };
+        if !loop_mode.is_valid(duration) {
+            return Err(DextreamerError::LoopChange(loop_mode));
+        }
+
+        let previous_loop_mode = std::mem::replace(&mut self.loop_mode, loop_mode);
+        self.loop_iterations = 0;
+
+        // The loop is applied with the first seek once the media is loaded
+        if self.is_loaded && loop_mode != previous_loop_mode {
+            let position = match (loop_mode.bounds(duration), self.rate > 0.0) {
+                (Some((start, _)), true) if loop_mode != LoopMode::File => start,
+                (Some((_, Some(stop))), false) => stop,
+                _ => self.current_position(),
+            };
+            if self
+                .seek(
+                    self.rate,
+                    seconds_to_clock_time(position),
+                    SeekMode::Accurate,
+                )
+                .is_err()
+            {
+                self.loop_mode = previous_loop_mode;
+                return Err(DextreamerError::LoopChange(loop_mode));
+            }
+            self.schedule_position_update();
+        }
+
+        Ok(())
+    }
+
+    /// Starts the next iteration of the loop once the end of the looped part has been reached.
+    fn wrap_loop(&mut self) -> Result<(), DextreamerError> {
+        let Some((start, stop)) = self.loop_mode.bounds(self.duration()) else {
+            return Ok(());
+        };
+        let position = if self.segment_rate > 0.0 {
+            start
+        } else {
+            stop.unwrap_or_else(|| self.duration())
+        };
+
+        // Without flushing, the new segment is queued right after the current one, so that the
+        //  playback wraps around without a gap
+        let flags = gst::SeekFlags::ACCURATE;
+        if self
+            .seek_with_flags(self.segment_rate, seconds_to_clock_time(position), flags)
+            .is_err()
+        {
+            // The pipeline doesn't post `Eos` after a segment seek, the end is reported instead
+            let loop_mode = std::mem::take(&mut self.loop_mode);
+            self.sender.send(VideoStreamEvent::EndOfStream)?;
+            return Err(DextreamerError::LoopChange(loop_mode));
+        }
+
+        self.loop_iterations += 1;
+        self.sender
+            .send(VideoStreamEvent::LoopIteration(self.loop_iterations))
+    }
+
+    fn duration(&self) -> f64 {
+        if self.last_duration > 0.0 {
+            self.last_duration
+        } else {
+            video_duration(&self.playbin_pipeline)
+        }
+    }
+
+    /// Seeks to `target` at the current rate. If the media is still loading or another seek is
+    /// in flight, the seek is only executed once the media is loaded or the other seek has
+    /// completed, merged with the other seeks requested meanwhile.
+    fn request_seek(&mut self, target: SeekTarget, mode: SeekMode) -> Result<(), DextreamerError> {
+        if !self.is_bus_running {
+            let requested = target.position(self.current_position(), self.duration());
+            return self.sender.send(VideoStreamEvent::SeekFailed { requested });
+        }
+        if !self.is_loaded || self.seek_in_flight.is_some() {
+            let target = match self.queued_seek {
+                Some((queued_target, _)) => queued_target.then(target, self.duration()),
+                None => target,
+            };
+            self.queued_seek = Some((target, mode));
+            return Ok(());
+        }
+
+        let (current_position, duration) = (self.current_position(), self.duration());
+        let Some(position) = target.checked_position(current_position, duration) else {
+            let requested = target.position(current_position, duration);
+            return self.sender.send(VideoStreamEvent::SeekFailed { requested });
+        };
+        if self
+            .seek(self.rate, seconds_to_clock_time(position), mode)
+            .is_err()
+        {
+            return self.sender.send(VideoStreamEvent::SeekFailed {
+                requested: position,
+            });
+        }
+        self.seek_in_flight = Some(SeekInFlight::new(Some(position)));
+
+        Ok(())
+    }
+
+    /// Reports the seek in flight once the pipeline has prerolled at its position, and executes
+    /// the seek queued behind it.
+    fn complete_seek(&mut self) -> Result<(), DextreamerError> {
+        let Some(seek_in_flight) = self.seek_in_flight.take() else {
+            return Ok(());
+        };
+
+        if let Some(requested) = seek_in_flight.requested {
+            let actual = video_position(&self.playbin_pipeline).unwrap_or(requested);
+            self.sender
+                .send(VideoStreamEvent::SeekCompleted { requested, actual })?;
+            self.update_position()?;
+        }
+
+        match self.queued_seek.take() {
+            Some((target, mode)) => self.request_seek(target, mode),
+            None => Ok(()),
+        }
+    }
+
+    /// Reports the seek in flight and the one queued behind it as failed, once the pipeline
+    /// can't preroll after them anymore.
+    fn fail_seeks(&mut self) -> Result<(), DextreamerError> {
+        if let Some(requested) = self.seek_in_flight.take().and_then(|seek| seek.requested) {
+            self.sender
+                .send(VideoStreamEvent::SeekFailed { requested })?;
+        }
+        if let Some((target, _)) = self.queued_seek.take() {
+            let requested = target.position(self.current_position(), self.duration());
+            self.sender
+                .send(VideoStreamEvent::SeekFailed { requested })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the position requested by the seek in flight, if any.
+    fn requested_position(&self) -> Option<f64> {
+        self.seek_in_flight.and_then(|seek| seek.requested)
+    }
+
+    /// Changes the playback rate, restarting the playback from the current position.
+    fn set_rate(&mut self, rate: f64) -> Result<(), DextreamerError> {
+        if rate == 0.0 || !rate.is_finite() {
+            return Err(DextreamerError::RateChange(rate));
+        }
+        // The pitch of the audio can't be preserved without `scaletempo`
+        let has_audio_filter = self
+            .playbin_pipeline
+            .property::<Option<gst::Element>>("audio-filter")
+            .is_some();
+        if rate != 1.0 && self.config.audio_sink != AudioSink::Disabled && !has_audio_filter {
+            return Err(DextreamerError::RateChange(rate));
+        }
+
+        // The rate is applied with the first seek once the media is loaded
+        if self.is_loaded {
+            let position = self
+                .requested_position()
+                .or_else(|| video_position(&self.playbin_pipeline))
+                .or(self.last_position)
+                .unwrap_or_default();
+            self.seek(rate, seconds_to_clock_time(position), SeekMode::Accurate)
+                .map_err(|_| DextreamerError::RateChange(rate))?;
+            self.schedule_position_update();
+        }
+
+        self.rate = rate;
+        self.sender.send(VideoStreamEvent::RateChanged(rate))
+    }
+
+    /// Returns the position requested by the seek in flight, or else the position of the frame
+    /// currently displayed, falling back to the playback position if no frame has been delivered
+    /// yet.
+    fn current_position(&self) -> f64 {
+        self.requested_position()
+            .or_else(|| self.video_sink.stepping().last_frame_position())
+            .or_else(|| video_position(&self.playbin_pipeline))
+            .or(self.last_position)
+            .unwrap_or_default()
+    }
+
+    /// Pauses the stream and moves by `frames` frames. Stepping against the direction of the
+    /// segment needs a seek in the other direction first: in reverse, it already lands on the
+    /// previous frame, while in forward, it lands on the current one again.
+    fn step_frames(&mut self, frames: i32) -> Result<(), DextreamerError> {
+        if frames == 0 || !self.is_loaded {
+            return Ok(());
+        }
+        if !self.is_paused.load(Ordering::SeqCst) {
+            self.set_playing_state(PlayingState::Paused)?;
+        }
+
+        let direction = f64::from(frames.signum());
+        let mut remaining_frames = frames.unsigned_abs();
+        let needs_seek = self.segment_rate.signum() != direction;
+        if needs_seek && direction < 0.0 {
+            remaining_frames -= 1;
+        }
+        let expected_frames = u32::from(needs_seek) + u32::from(remaining_frames > 0);
+
+        if needs_seek {
+            let position = seconds_to_clock_time(self.current_position());
+            self.seek_expecting_frames(
+                direction * self.rate.abs(),
+                position,
+                SeekMode::Accurate,
+                expected_frames,
+            )
+            .map_err(|_| DextreamerError::StateChange(PlayingState::Paused))?;
+        } else {
+            // Set before stepping, as the frame can be delivered before `step` returns
+            self.video_sink.stepping().expect_frames(expected_frames);
+        }
+        if remaining_frames > 0 && !self.video_sink.step(remaining_frames) {
+            self.video_sink.stepping().expect_frames(0);
+            return Err(DextreamerError::StateChange(PlayingState::Paused));
+        }
+
+        Ok(())
+    }
+
+    /// Changes the playing state, and completes `completion` once the pipeline has reached it.
+    #[cfg(feature = "async")]
+    fn await_playing_state(
+        &mut self,
+        playing_state: PlayingState,
+        completion: CompletionSender,
+    ) -> Result<(), DextreamerError> {
+        if let Err(err) = self.set_playing_state(playing_state) {
+            completion.complete(Err(err.clone()));
+            return Err(err);
+        }
+
+        self.pending_state_changes.push((playing_state, completion));
+        self.resolve_pending_state_changes();
+
+        Ok(())
+    }
+
+    /// Completes the awaited state changes once the pipeline has settled in their state. The ones
+    /// superseded by a change to another state fail.
+    #[cfg(feature = "async")]
+    fn resolve_pending_state_changes(&mut self) {
+        let (result, current_state, pending_state) =
+            self.playbin_pipeline.state(gst::ClockTime::ZERO);
+        let is_settled = result.is_ok() && pending_state == gst::State::VoidPending;
+        let requested_state = if self.is_paused.load(Ordering::SeqCst) {
+            PlayingState::Paused
+        } else {
+            PlayingState::Playing
+        };
+
+        let pending_state_changes = std::mem::take(&mut self.pending_state_changes);
+        for (playing_state, completion) in pending_state_changes {
+            if result.is_err() || playing_state != requested_state {
+                completion.complete(Err(DextreamerError::StateChange(playing_state)));
+            } else if is_settled && current_state == to_gst_state(playing_state) {
+                completion.complete(Ok(()));
+            } else {
+                self.pending_state_changes.push((playing_state, completion));
+            }
+        }
+    }
+
+    fn handle_action(&mut self, video_action: VideoStreamAction) -> Result<bool, DextreamerError> {
+        let playbin_pipeline = &self.playbin_pipeline;
+
+        match video_action {
+            VideoStreamAction::SetCurrentSubtitleTrack(track_id) => {
+                // Setting a subtitle track, or disabling subtitles altogether
+                if let Some(subtitle_track_id) = track_id {
+                    playbin_pipeline.set_property("current-text", subtitle_track_id as i32);
+                }
+                set_playbin_flag(playbin_pipeline, PLAYBIN_FLAG_TEXT, track_id.is_some());
+                self.sender
+                    .send(VideoStreamEvent::CurrentSubtitleTrackChanged(track_id))?;
+            }
+            VideoStreamAction::SetCurrentAudioTrack(audio_track_id) => {
+                // Setting an audio track, or disabling audio altogether
+                if let Some(audio_track_id) = audio_track_id {
+                    playbin_pipeline.set_property("current-audio", audio_track_id as i32);
+                }
+                set_playbin_flag(
+                    playbin_pipeline,
+                    PLAYBIN_FLAG_AUDIO,
+                    audio_track_id.is_some(),
+                );
+                self.sender
+                    .send(VideoStreamEvent::CurrentAudioTrackChanged(audio_track_id))?;
+            }
+            VideoStreamAction::SetVolume(volume) => {
+                self.sender.send(VideoStreamEvent::VolumeChanged(volume))?;
+                playbin_pipeline.set_property("volume", volume as f64);
+            }
+            VideoStreamAction::SetPlay => {
+                // The frames delivered while playing are not the result of a step
+                self.video_sink.stepping().expect_frames(0);
+                if self.is_loaded && self.segment_rate != self.rate {
+                    // Resumes in the direction of the playback after stepping against it
+                    let position = seconds_to_clock_time(self.current_position());
+                    self.seek(self.rate, position, SeekMode::Accurate)
+                        .map_err(|_| DextreamerError::StateChange(PlayingState::Playing))?;
+                }
+                self.set_playing_state(PlayingState::Playing)?;
+                #[cfg(feature = "async")]
+                self.resolve_pending_state_changes();
+            }
+            VideoStreamAction::SetPause => {
+                self.set_playing_state(PlayingState::Paused)?;
+                #[cfg(feature = "async")]
+                self.resolve_pending_state_changes();
+            }
+            VideoStreamAction::SeekToSeconds(_) | VideoStreamAction::Seek { .. } => {
+                if let Some((target, mode)) = video_action.seek_request() {
+                    self.request_seek(target, mode)?;
+                }
+            }
+            VideoStreamAction::SetRate(rate) => self.set_rate(rate)?,
+            VideoStreamAction::StepFrames(frames) => self.step_frames(frames)?,
+            VideoStreamAction::SetLoop(loop_mode) => self.set_loop(loop_mode)?,
+            VideoStreamAction::SetOutputSize(output_size) => {
+                self.video_sink.set_output_size(output_size)?;
+                self.sender
+                    .send(VideoStreamEvent::OutputSizeChanged(output_size))?;
+            }
+            VideoStreamAction::Close => {
+                // The pipeline is moved to the `Null` state once the message loop exits
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn handle_message(&mut self, message: InternalMessage) -> Result<bool, DextreamerError> {
+        let playbin_pipeline = &self.playbin_pipeline;
+
+        match message {
+            InternalMessage::VideoStreamAction(video_action) => self.handle_action(video_action),
+            InternalMessage::VideoLoaded => {
+                if let Some(Some(audio_track_id)) = self.config.audio_track {
+                    playbin_pipeline.set_property("current-audio", audio_track_id as i32);
+                }
+                if let Some(Some(subtitle_track_id)) = self.config.subtitle_track {
+                    playbin_pipeline.set_property("current-text", subtitle_track_id as i32);
+                }
+
+                let playing_state = if self.is_paused.load(Ordering::SeqCst) {
+                    PlayingState::Paused
+                } else {
+                    PlayingState::Playing
+                };
+
+                let mut video_state = VideoInfo {
+                    title: "Test title.mkv".to_string(),
+                    current_subtitle_track: current_subtitle_track(playbin_pipeline),
+                    current_audio_track: current_audio_track(playbin_pipeline),
+                    volume: self.config.volume,
+                    subtitle_tracks: subtitle_tracks(playbin_pipeline),
+                    audio_tracks: audio_tracks(playbin_pipeline),
+                    playing_state,
+                    rate: self.rate,
+                    duration: video_duration(playbin_pipeline),
+                    current_position: 0.0,
+                };
+
+                // A range set before the media was loaded may not overlap it
+                let loop_result = if self.loop_mode.is_valid(video_state.duration) {
+                    Ok(())
+                } else {
+                    Err(DextreamerError::LoopChange(std::mem::take(
+                        &mut self.loop_mode,
+                    )))
+                };
+
+                // A rate or a loop set before the media was loaded is applied now. Playing
+                //  backwards starts from the end of the media, or of the looped part.
+                let rate_result = if self.rate == 1.0 && self.loop_mode == LoopMode::Off {
+                    Ok(())
+                } else {
+                    let rate = self.rate;
+                    let position = if rate > 0.0 {
+                        0.0
+                    } else {
+                        video_state.duration
+                    };
+                    self.seek(rate, seconds_to_clock_time(position), SeekMode::Accurate)
+                        .map_err(|_| {
+                            if rate == 1.0 {
+                                DextreamerError::LoopChange(std::mem::take(&mut self.loop_mode))
+                            } else {
+                                self.rate = 1.0;
+                                video_state.rate = 1.0;
+                                DextreamerError::RateChange(rate)
+                            }
+                        })
+                };
+
+                self.is_loaded = true;
+                self.last_duration = video_state.duration;
+                self.next_position_update = Instant::now();
+                self.sender
+                    .send(VideoStreamEvent::VideoLoaded(video_state))?;
+                // A seek requested while loading is resolved now that the duration is known
+                if let Some((target, mode)) = self.queued_seek.take() {
+                    self.request_seek(target, mode)?;
+                }
+                loop_result.and(rate_result)?;
+                // The state changes that happen while loading are not forwarded by the bus thread
+                #[cfg(feature = "async")]
+                self.resolve_pending_state_changes();
+
+                Ok(false)
+            }
+            InternalMessage::LoadFailed(err) => {
+                // Stops whatever part of the pipeline could be started
+                let _ = set_pipeline_state(playbin_pipeline, PlayingState::Stopped);
+                #[cfg(feature = "async")]
+                for (_, completion) in self.pending_state_changes.drain(..) {
+                    completion.complete(Err(err.clone()));
+                }
+                self.sender.send(VideoStreamEvent::LoadFailed(err))?;
+                // Seeks requested while loading are never executed, and the ones requested
+                //  from now on fail right away
+                self.is_bus_running = false;
+                self.fail_seeks()?;
+
+                Ok(false)
+            }
+            InternalMessage::PipelineStateChanged => {
+                #[cfg(feature = "async")]
+                self.resolve_pending_state_changes();
+
+                Ok(false)
+            }
+            #[cfg(feature = "async")]
+            InternalMessage::AwaitPlayingState(playing_state, completion) => {
+                self.await_playing_state(playing_state, completion)?;
+
+                Ok(false)
+            }
+            InternalMessage::SegmentDone => {
+                self.wrap_loop()?;
+
+                Ok(false)
+            }
+            InternalMessage::AsyncDone => {
+                self.complete_seek()?;
+
+                Ok(false)
+            }
+            InternalMessage::FrameStepped(position) => {
+                // Falls back to the position query if the frame has no timestamp
+                let Some(position) = position.or_else(|| video_position(playbin_pipeline)) else {
+                    return Ok(false);
+                };
+                self.last_position = Some(position);
+                self.sender
+                    .send(VideoStreamEvent::PositionChanged(position))?;
+
+                Ok(false)
+            }
+            InternalMessage::DurationChanged => {
+                if self.is_loaded {
+                    self.update_duration()?;
+                }
+
+                Ok(false)
+            }
+            InternalMessage::BusStopped => {
+                self.is_bus_running = false;
+                self.fail_seeks()?;
+
+                Ok(false)
+            }
+        }
+    }
+
+    /// Returns when the control thread has to wake up without a message: to poll the position,
+    /// or to fail the seek in flight once it has timed out.
+    fn next_deadline(&self) -> Option<Instant> {
+        let position_update = self
+            .needs_position_update()
+            .then_some(self.next_position_update);
+        let seek_deadline = self.seek_in_flight.map(|seek| seek.deadline);
+
+        position_update.into_iter().chain(seek_deadline).min()
+    }
+
+    fn handle_deadlines(&mut self) -> Result<bool, DextreamerError> {
+        let now = Instant::now();
+        if self.seek_in_flight.is_some_and(|seek| seek.deadline <= now) {
+            self.fail_seeks()?;
+        }
+        if self.needs_position_update() && self.next_position_update <= now {
+            self.update_position()?;
+        }
+
+        Ok(false)
+    }
+
+    /// Handles the actions and internal messages until the stream is closed, and then tears the
+    /// pipeline down.
+    pub(crate) fn run(
+        mut self,
+        playbin_message_bus: &Bus,
+        bus_thread_handle: JoinHandle<()>,
+        internal_receiver: Receiver<InternalMessage>,
+    ) {
+        loop {
+            let result = if let Some(deadline) = self.next_deadline() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match internal_receiver.recv_timeout(timeout) {
+                    Ok(message) => self.handle_message(message),
+                    Err(RecvTimeoutError::Timeout) => self.handle_deadlines(),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match internal_receiver.recv() {
+                    Ok(message) => self.handle_message(message),
+                    Err(_) => break,
+                }
+            };
+
+            match result {
+                Ok(false) => (),
+                Ok(true) | Err(DextreamerError::ChannelDisconnected) => break,
+                Err(err) => {
+                    if self.sender.send(VideoStreamEvent::Error(err)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.video_sink.close_frame_queue();
+
+        // The shutdown message has to be posted before the pipeline goes to the `Null` state,
+        //  as the bus drops all pending messages at that point.
+        let _ = playbin_message_bus.post(gst::message::Application::new(
+            gst::Structure::new_empty(SHUTDOWN_MESSAGE_NAME),
+        ));
+        // The bus thread only panics if there is a bug in this crate, in which case it has already been reported
+        let _ = bus_thread_handle.join();
+
+        if let Err(err) = set_pipeline_state(&self.playbin_pipeline, PlayingState::Stopped) {
+            let _ = self.sender.send(VideoStreamEvent::Error(err));
+        }
+        self.video_sink.stop_handler_worker();
+        let _ = self.sender.send(VideoStreamEvent::Closed);
+        self.sender.close();
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{HandlerId, LoopMode, PlayingState};
+
+/// `DextreamerError` represents the errors that can occur while opening or controlling a video stream.
+///
+/// # Variants
+///
+/// * `Init(String)`: GStreamer could not be initialized. Contains the error reported by GStreamer.
+/// * `MissingElement(String)`: A GStreamer element required by the stream is not installed. Contains the element factory name.
+/// * `StateChange(PlayingState)`: The pipeline failed to change to the requested state.
+/// * `LoopChange(LoopMode)`: The pipeline failed to loop the playback as requested, e.g. because the range is empty or the media doesn't support segment seeks.
+/// * `RateChange(f64)`: The playback rate can't be changed to the requested one, e.g. because it is zero, the media doesn't support it or the `scaletempo` element is missing.
+/// * `InvalidUri { uri, reason }`: The provided URI is malformed or no installed element can handle it.
+/// * `ChannelDisconnected`: The other side of the stream's channel has been dropped.
+/// * `Pipeline { source_element, message, debug }`: An element of the pipeline reported an error while playing.
+/// * `LoadTimeout(Duration)`: The media did not finish loading within the configured timeout.
+/// * `FrameHandler(String)`: A `FrameHandlerMut` failed to handle a frame. Contains the error it returned.
+/// * `UnknownFrameHandler(HandlerId)`: No frame handler with the given id has been added to the stream, or it has already been removed.
+/// * `FrameBranch(String)`: The branch of an added frame handler could not be linked into the pipeline. Contains the reason.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DextreamerError {
+    /// GStreamer could not be initialized. Contains the error reported by GStreamer.
+    Init(String),
+    /// A GStreamer element required by the stream is not installed. Contains the element factory name.
+    MissingElement(String),
+    /// The pipeline failed to change to the requested state.
+    StateChange(PlayingState),
+    /// The pipeline failed to loop the playback as requested, e.g. because the range is empty or the media doesn't support segment seeks.
+    LoopChange(LoopMode),
+    /// The playback rate can't be changed to the requested one, e.g. because it is zero, the media doesn't support it or the `scaletempo` element is missing.
+    RateChange(f64),
+    /// The provided URI is malformed or no installed element can handle it.
+    InvalidUri { uri: String, reason: String },
+    /// The other side of the stream's channel has been dropped.
+    ChannelDisconnected,
+    /// An element of the pipeline reported an error while playing.
+    Pipeline {
+        /// The path of the element that reported the error, if known.
+        source_element: Option<String>,
+        message: String,
+        /// Additional debug information, meant for developers rather than users.
+        debug: Option<String>,
+    },
+    /// The media did not finish loading within the configured timeout.
+    LoadTimeout(Duration),
+    /// A `FrameHandlerMut` failed to handle a frame. Contains the error it returned.
+    FrameHandler(String),
+    /// No frame handler with the given id has been added to the stream, or it has already been removed.
+    UnknownFrameHandler(HandlerId),
+    /// The branch of an added frame handler could not be linked into the pipeline. Contains the reason.
+    FrameBranch(String),
+}
+
+impl fmt::Display for DextreamerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DextreamerError::Init(reason) => {
+                write!(f, "failed to initialize gstreamer: {reason}")
+            }
+            DextreamerError::MissingElement(factory_name) => {
+                write!(f, "gstreamer element `{factory_name}` is not available")
+            }
+            DextreamerError::StateChange(state) => {
+                write!(f, "failed to change the pipeline state to {state:?}")
+            }
+            DextreamerError::LoopChange(loop_mode) => {
+                write!(f, "failed to change the loop mode to {loop_mode:?}")
+            }
+            DextreamerError::RateChange(rate) => {
+                write!(f, "failed to change the playback rate to {rate}")
+            }
+            DextreamerError::InvalidUri { uri, reason } => {
+                write!(f, "invalid uri `{uri}`: {reason}")
+            }
+            DextreamerError::ChannelDisconnected => write!(f, "the stream channel is disconnected"),
+            DextreamerError::Pipeline {
+                source_element: Some(source_element),
+                message,
+                ..
+            } => write!(f, "error from {source_element}: {message}"),
+            DextreamerError::Pipeline { message, .. } => write!(f, "pipeline error: {message}"),
+            DextreamerError::LoadTimeout(timeout) => {
+                write!(f, "the media did not load within {timeout:?}")
+            }
+            DextreamerError::FrameHandler(reason) => {
+                write!(f, "the frame handler failed: {reason}")
+            }
+            DextreamerError::UnknownFrameHandler(handler_id) => {
+                write!(f, "unknown frame handler: {handler_id:?}")
+            }
+            DextreamerError::FrameBranch(reason) => {
+                write!(f, "failed to add the frame handler branch: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DextreamerError {}
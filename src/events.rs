@@ -0,0 +1,293 @@
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::channel::Channel;
+use crate::{DextreamerError, VideoStreamEvent};
+
+/// `EventMask` selects the events a video stream emits, see `VideoStreamBuilder::event_mask`.
+///
+/// Masks are combined with `|`. `VideoStreamEvent::VideoLoaded`, `VideoStreamEvent::LoadFailed`,
+/// `VideoStreamEvent::Error` and `VideoStreamEvent::Closed` are always emitted.
+///
+/// # Example
+///
+/// ```
+/// use dextreamer::EventMask;
+///
+/// let mask = EventMask::ALL.without(EventMask::NEW_FRAME);
+/// assert!(mask.contains(EventMask::POSITION));
+/// assert!(!mask.contains(EventMask::NEW_FRAME));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventMask(u32);
+
+impl EventMask {
+    /// No optional events.
+    pub const NONE: Self = Self(0);
+    /// `VideoStreamEvent::NewFrame`.
+    pub const NEW_FRAME: Self = Self(1 << 0);
+    /// `VideoStreamEvent::PositionChanged` and `VideoStreamEvent::DurationChanged`.
+    pub const POSITION: Self = Self(1 << 1);
+    /// `VideoStreamEvent::PlayingStateChanged`, `VideoStreamEvent::RateChanged`,
+    /// `VideoStreamEvent::EndOfStream` and `VideoStreamEvent::LoopIteration`.
+    pub const PLAYBACK: Self = Self(1 << 2);
+    /// `VideoStreamEvent::CurrentAudioTrackChanged` and `VideoStreamEvent::CurrentSubtitleTrackChanged`.
+    pub const TRACKS: Self = Self(1 << 3);
+    /// `VideoStreamEvent::VolumeChanged`.
+    pub const VOLUME: Self = Self(1 << 4);
+    /// `VideoStreamEvent::OutputSizeChanged`.
+    pub const OUTPUT_SIZE: Self = Self(1 << 5);
+    /// `VideoStreamEvent::Warning` and `VideoStreamEvent::Info`.
+    pub const MESSAGES: Self = Self(1 << 6);
+    /// `VideoStreamEvent::SeekCompleted` and `VideoStreamEvent::SeekFailed`.
+    pub const SEEK: Self = Self(1 << 7);
+    /// All the events.
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// Whether all the events of `other` are part of this mask.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns this mask without the events of `other`.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The mask an event belongs to, or `None` if the event is always emitted.
+    fn of(event: &VideoStreamEvent) -> Option<Self> {
+        match event {
+            VideoStreamEvent::NewFrame => Some(Self::NEW_FRAME),
+            VideoStreamEvent::PositionChanged(_) | VideoStreamEvent::DurationChanged(_) => {
+                Some(Self::POSITION)
+            }
+            VideoStreamEvent::PlayingStateChanged(_)
+            | VideoStreamEvent::RateChanged(_)
+            | VideoStreamEvent::EndOfStream
+            | VideoStreamEvent::LoopIteration(_) => Some(Self::PLAYBACK),
+            VideoStreamEvent::CurrentAudioTrackChanged(_)
+            | VideoStreamEvent::CurrentSubtitleTrackChanged(_) => Some(Self::TRACKS),
+            VideoStreamEvent::VolumeChanged(_) => Some(Self::VOLUME),
+            VideoStreamEvent::SeekCompleted { .. } | VideoStreamEvent::SeekFailed { .. } => {
+                Some(Self::SEEK)
+            }
+            VideoStreamEvent::OutputSizeChanged(_) => Some(Self::OUTPUT_SIZE),
+            VideoStreamEvent::Warning { .. } | VideoStreamEvent::Info { .. } => {
+                Some(Self::MESSAGES)
+            }
+            VideoStreamEvent::VideoLoaded(_)
+            | VideoStreamEvent::LoadFailed(_)
+            | VideoStreamEvent::Error(_)
+            | VideoStreamEvent::Closed => None,
+        }
+    }
+}
+
+impl Default for EventMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for EventMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for EventMask {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// Whether only the latest pending event of this kind is worth keeping.
+fn is_coalesced(event: &VideoStreamEvent) -> bool {
+    matches!(
+        event,
+        VideoStreamEvent::NewFrame | VideoStreamEvent::PositionChanged(_)
+    )
+}
+
+/// Creates the channel carrying the events from the stream threads to the `VideoStream`.
+///
+/// Unlike `std::sync::mpsc`, the receiver can also be polled from an async task. Only the events
+/// selected by `mask` are sent, and a pending `NewFrame` or `PositionChanged` event is replaced by
+/// the next one of the same kind, so that a consumer falling behind only gets the latest one.
+pub(crate) fn event_channel(mask: EventMask) -> (EventSender, EventReceiver) {
+    let channel = Arc::new(Channel::new(()));
+
+    (
+        EventSender {
+            mask,
+            channel: channel.clone(),
+        },
+        EventReceiver { channel },
+    )
+}
+
+#[derive(Clone)]
+pub(crate) struct EventSender {
+    mask: EventMask,
+    channel: Arc<Channel<VideoStreamEvent, ()>>,
+}
+
+impl EventSender {
+    /// Returns `DextreamerError::ChannelDisconnected` if the receiver has been dropped or the
+    /// channel has been closed.
+    pub(crate) fn send(&self, event: VideoStreamEvent) -> Result<(), DextreamerError> {
+        let is_masked_out = EventMask::of(&event).is_some_and(|mask| !self.mask.contains(mask));
+
+        let mut state = self.channel.lock();
+        if state.closed {
+            return Err(DextreamerError::ChannelDisconnected);
+        }
+        if is_masked_out {
+            return Ok(());
+        }
+
+        if is_coalesced(&event) {
+            let kind = std::mem::discriminant(&event);
+            state
+                .items
+                .retain(|pending| std::mem::discriminant(pending) != kind);
+        }
+        self.channel.push(&mut state, event);
+
+        Ok(())
+    }
+
+    /// Closes the channel once the stream is closed. The receiver gets the pending events, and
+    /// then `None`. The senders held by the pipeline can outlive the stream, so this doesn't wait
+    /// for all of them to be dropped.
+    pub(crate) fn close(&self) {
+        let mut state = self.channel.lock();
+        self.channel.close(&mut state);
+    }
+}
+
+pub(crate) struct EventReceiver {
+    channel: Arc<Channel<VideoStreamEvent, ()>>,
+}
+
+impl EventReceiver {
+    /// Blocks until the next event. Returns `None` once the channel is closed and all the events
+    /// have been received.
+    pub(crate) fn recv(&self) -> Option<VideoStreamEvent> {
+        self.channel.recv()
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<VideoStreamEvent> {
+        self.channel.try_recv()
+    }
+
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Option<VideoStreamEvent> {
+        self.channel.recv_timeout(timeout)
+    }
+
+    /// Returns the next event if there is one, or registers the task to be woken up by the next
+    /// event otherwise.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<VideoStreamEvent>> {
+        self.channel.poll_recv(cx)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        self.channel.close(&mut state);
+        state.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_events(receiver: &EventReceiver) -> Vec<VideoStreamEvent> {
+        std::iter::from_fn(|| receiver.try_recv()).collect()
+    }
+
+    #[test]
+    fn masked_event_is_not_delivered() {
+        let (sender, receiver) = event_channel(EventMask::ALL.without(EventMask::VOLUME));
+
+        sender.send(VideoStreamEvent::VolumeChanged(0.5)).unwrap();
+        sender.send(VideoStreamEvent::EndOfStream).unwrap();
+
+        let events = pending_events(&receiver);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], VideoStreamEvent::EndOfStream));
+    }
+
+    #[test]
+    fn error_and_closed_are_never_masked() {
+        let (sender, receiver) = event_channel(EventMask::NONE);
+
+        sender.send(VideoStreamEvent::EndOfStream).unwrap();
+        sender
+            .send(VideoStreamEvent::Error(
+                DextreamerError::ChannelDisconnected,
+            ))
+            .unwrap();
+        sender.send(VideoStreamEvent::Closed).unwrap();
+
+        let events = pending_events(&receiver);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], VideoStreamEvent::Error(_)));
+        assert!(matches!(events[1], VideoStreamEvent::Closed));
+    }
+
+    #[test]
+    fn only_latest_coalesced_event_is_kept() {
+        let (sender, receiver) = event_channel(EventMask::ALL);
+
+        sender.send(VideoStreamEvent::PositionChanged(1.0)).unwrap();
+        sender.send(VideoStreamEvent::NewFrame).unwrap();
+        sender.send(VideoStreamEvent::VolumeChanged(0.5)).unwrap();
+        sender.send(VideoStreamEvent::PositionChanged(2.0)).unwrap();
+        sender.send(VideoStreamEvent::EndOfStream).unwrap();
+        sender.send(VideoStreamEvent::NewFrame).unwrap();
+        sender.send(VideoStreamEvent::VolumeChanged(1.0)).unwrap();
+
+        let events = pending_events(&receiver);
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[0], VideoStreamEvent::VolumeChanged(volume) if volume == 0.5));
+        assert!(
+            matches!(events[1], VideoStreamEvent::PositionChanged(position) if position == 2.0)
+        );
+        assert!(matches!(events[2], VideoStreamEvent::EndOfStream));
+        assert!(matches!(events[3], VideoStreamEvent::NewFrame));
+        assert!(matches!(events[4], VideoStreamEvent::VolumeChanged(volume) if volume == 1.0));
+    }
+
+    #[test]
+    fn send_fails_once_receiver_is_dropped() {
+        let (sender, receiver) = event_channel(EventMask::ALL);
+        drop(receiver);
+
+        assert!(sender.send(VideoStreamEvent::Closed).is_err());
+    }
+
+    #[test]
+    fn recv_ends_once_channel_is_closed() {
+        let (sender, receiver) = event_channel(EventMask::ALL);
+        // A clone kept alive, like the ones held by the pipeline
+        let _pipeline_sender = sender.clone();
+        sender.send(VideoStreamEvent::EndOfStream).unwrap();
+        sender.close();
+
+        assert!(sender.send(VideoStreamEvent::Closed).is_err());
+        assert!(matches!(
+            receiver.recv(),
+            Some(VideoStreamEvent::EndOfStream)
+        ));
+        assert!(receiver.recv().is_none());
+    }
+}
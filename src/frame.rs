@@ -0,0 +1,217 @@
+use gstreamer as gst;
+use gstreamer_video as gst_video;
+
+use std::time::Duration;
+
+use crate::{FrameData, PixelFormat};
+
+pub(crate) fn clock_time_to_duration(clock_time: gst::ClockTime) -> Duration {
+    Duration::from_nanos(clock_time.nseconds())
+}
+
+/// Implements the accessors shared by `VideoFrameRef` and `Frame`, which both hold a mapped
+/// gstreamer frame in `frame`, along with its `format` and `index`.
+macro_rules! impl_frame_accessors {
+    ($frame_type:ident $(<$lifetime:lifetime>)?) => {
+        impl<$($lifetime)?> $frame_type<$($lifetime)?> {
+            /// The width of the frame in pixels.
+            pub fn width(&self) -> u32 {
+                self.frame.width()
+            }
+
+            /// The height of the frame in pixels.
+            pub fn height(&self) -> u32 {
+                self.frame.height()
+            }
+
+            /// The pixel format of the frame.
+            pub fn format(&self) -> PixelFormat {
+                self.format
+            }
+
+            /// The number of planes the pixels are stored in.
+            pub fn plane_count(&self) -> usize {
+                self.frame.n_planes() as usize
+            }
+
+            /// The raw data of the plane with the given index, or `None` if there is no such
+            /// plane.
+            pub fn plane(&self, index: usize) -> Option<&[u8]> {
+                self.frame.plane_data(index as u32).ok()
+            }
+
+            /// The number of bytes between the starts of two consecutive rows of the plane with
+            /// the given index, or `None` if there is no such plane.
+            pub fn stride(&self, index: usize) -> Option<usize> {
+                self.frame
+                    .plane_stride()
+                    .get(index)
+                    .map(|stride| *stride as usize)
+            }
+
+            /// The pixel aspect ratio of the frame, as a `(numerator, denominator)` pair. Pixels
+            /// are square if both are equal.
+            pub fn pixel_aspect_ratio(&self) -> (u32, u32) {
+                let pixel_aspect_ratio = self.frame.info().par();
+
+                (
+                    pixel_aspect_ratio.numer() as u32,
+                    pixel_aspect_ratio.denom() as u32,
+                )
+            }
+
+            /// The presentation timestamp of the frame, if known.
+            pub fn pts(&self) -> Option<Duration> {
+                self.frame.buffer().pts().map(clock_time_to_duration)
+            }
+
+            /// How long the frame is displayed for, if known.
+            pub fn duration(&self) -> Option<Duration> {
+                self.frame.buffer().duration().map(clock_time_to_duration)
+            }
+
+            /// The index of the frame since the stream was opened. It is increased by one for
+            /// every frame delivered to the handler.
+            pub fn index(&self) -> u64 {
+                self.index
+            }
+        }
+    };
+}
+
+/// `VideoFrameRef` is a decoded video frame borrowed from the gstreamer pipeline. It is only
+/// valid for the duration of the `FrameHandler::handle_new_frame` call.
+///
+/// The pixels are stored in one or more planes, depending on the negotiated `PixelFormat`: packed
+/// formats such as `PixelFormat::Rgba` use a single plane, while planar formats such as
+/// `PixelFormat::I420` store each component in its own plane. Rows of a plane can be padded for
+/// alignment, so the rows have to be read using the plane's stride rather than the frame width.
+///
+/// # Example
+///
+/// ```
+/// use dextreamer::VideoFrameRef;
+///
+/// /// Copies the frame into a tightly packed RGBA buffer.
+/// fn to_packed_rgba(frame: &VideoFrameRef<'_>) -> Vec<u8> {
+///     let row_size = frame.width() as usize * 4;
+///     let stride = frame.stride(0).unwrap();
+///     let plane = frame.plane(0).unwrap();
+///
+///     plane
+///         .chunks(stride)
+///         .take(frame.height() as usize)
+///         .flat_map(|row| &row[..row_size])
+///         .copied()
+///         .collect()
+/// }
+/// ```
+pub struct VideoFrameRef<'a> {
+    frame: gst_video::VideoFrameRef<&'a gst::BufferRef>,
+    format: PixelFormat,
+    index: u64,
+}
+
+impl<'a> VideoFrameRef<'a> {
+    pub(crate) fn new(
+        frame: gst_video::VideoFrameRef<&'a gst::BufferRef>,
+        format: PixelFormat,
+        index: u64,
+    ) -> Self {
+        Self {
+            frame,
+            format,
+            index,
+        }
+    }
+
+    /// Keeps the frame alive beyond the `FrameHandler::handle_new_frame` call, without copying
+    /// its pixels. See [`Frame`].
+    ///
+    /// Returns `None` if the buffer of the frame can't be mapped again.
+    pub fn retain(&self) -> Option<Frame> {
+        // Takes a new reference to the buffer rather than copying it
+        let buffer = self.frame.buffer().to_owned();
+        let frame = gst_video::VideoFrame::from_buffer_readable(buffer, self.frame.info()).ok()?;
+
+        Some(Frame {
+            frame,
+            format: self.format,
+            index: self.index,
+        })
+    }
+
+    /// Copies the frame into `frame_data`, reusing its allocations.
+    pub(crate) fn copy_to(&self, frame_data: &mut FrameData) {
+        frame_data.data.clear();
+        frame_data.strides.clear();
+        frame_data.offsets.clear();
+
+        for index in 0..self.plane_count() {
+            let (Some(plane), Some(stride)) = (self.plane(index), self.stride(index)) else {
+                continue;
+            };
+
+            frame_data.offsets.push(frame_data.data.len());
+            frame_data.strides.push(stride);
+            frame_data.data.extend_from_slice(plane);
+        }
+
+        frame_data.size = [self.width() as usize, self.height() as usize];
+        frame_data.format = self.format;
+        frame_data.pts = self.pts();
+        frame_data.duration = self.duration();
+        frame_data.index = self.index;
+    }
+}
+
+impl_frame_accessors!(VideoFrameRef<'a>);
+
+/// `Frame` is a decoded video frame that keeps the gstreamer buffer holding its pixels alive,
+/// created with [`VideoFrameRef::retain`].
+///
+/// Unlike `FrameData`, the pixels are not copied: they are read directly from the memory the
+/// decoder wrote them to, e.g. to upload them to a texture. The frame can be sent to another
+/// thread, and the buffer is released back to the decoder when the frame is dropped. Decoders
+/// only have a limited number of buffers, so holding on to too many frames stalls the decoding.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::mpsc::{self, Sender};
+///
+/// struct FrameForwarder(Sender<dextreamer::Frame>);
+///
+/// impl dextreamer::FrameHandler for FrameForwarder {
+///     fn handle_new_frame(&self, frame: &dextreamer::VideoFrameRef<'_>) {
+///         if let Some(frame) = frame.retain() {
+///             let _ = self.0.send(frame);
+///         }
+///     }
+/// }
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let video_stream =
+///     dextreamer::open_video("file:///home/user/my_video.mkv", FrameForwarder(sender))
+///         .expect("to open the video");
+///
+/// for frame in receiver {
+///     // Upload `frame.plane(0)` to a texture, the buffer is released when `frame` is dropped
+///     println!("Frame {}: {}x{}", frame.index(), frame.width(), frame.height());
+/// }
+/// ```
+pub struct Frame {
+    frame: gst_video::VideoFrame<gst_video::video_frame::Readable>,
+    format: PixelFormat,
+    index: u64,
+}
+
+impl Frame {
+    /// Borrows the frame as a `VideoFrameRef`, e.g. to pass it to code written for the frame
+    /// handler.
+    pub fn as_frame_ref(&self) -> VideoFrameRef<'_> {
+        VideoFrameRef::new(self.frame.as_video_frame_ref(), self.format, self.index)
+    }
+}
+
+impl_frame_accessors!(Frame);
@@ -1,3 +1,178 @@
+use std::fmt::Display;
+use std::sync::mpsc::Sender;
+
+use crate::events::EventSender;
+use crate::streamer::InternalMessage;
+use crate::{
+    DextreamerError, OutputSize, PixelFormat, VideoFrameRef, VideoStreamAction, VideoStreamEvent,
+};
+
+/// `FrameHandler` receives the decoded frames of a video stream.
+///
+/// `handle_new_frame` is called from the gstreamer streaming thread, so it should return quickly
+/// to avoid stalling the playback.
 pub trait FrameHandler: Send {
-    fn handle_new_frame(&self, frame_data: &[u8], frame_size: (u32, u32));
+    fn handle_new_frame(&self, frame: &VideoFrameRef<'_>);
+}
+
+/// `FrameControl` tells the stream what to do after a frame has been handled by a
+/// `FrameHandlerMut`.
+///
+/// # Variants
+///
+/// * `Continue`: Keep playing.
+/// * `Pause`: Pause the playback, as with `VideoStreamAction::SetPause`.
+/// * `Close`: Close the stream, as with `VideoStreamAction::Close`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameControl {
+    /// Keep playing.
+    #[default]
+    Continue,
+    /// Pause the playback, as with `VideoStreamAction::SetPause`.
+    Pause,
+    /// Close the stream, as with `VideoStreamAction::Close`.
+    Close,
+}
+
+/// `FrameHandlerMut` is a `FrameHandler` that can mutate its own state and control the stream.
+///
+/// Returning an error emits a `VideoStreamEvent::Error` containing
+/// `DextreamerError::FrameHandler` and pauses the playback. Like `FrameHandler::handle_new_frame`,
+/// `handle_new_frame` is called from the gstreamer streaming thread.
+///
+/// # Example
+///
+/// ```no_run
+/// use dextreamer::{FrameControl, FrameHandlerMut, VideoFrameRef};
+///
+/// struct FirstFrames {
+///     remaining: usize,
+/// }
+///
+/// impl FrameHandlerMut for FirstFrames {
+///     type Error = String;
+///
+///     fn handle_new_frame(&mut self, frame: &VideoFrameRef<'_>) -> Result<FrameControl, String> {
+///         let plane = frame.plane(0).ok_or("the frame has no planes")?;
+///         println!("Frame {}: {} bytes", frame.index(), plane.len());
+///
+///         self.remaining -= 1;
+///         if self.remaining == 0 {
+///             return Ok(FrameControl::Close);
+///         }
+///         Ok(FrameControl::Continue)
+///     }
+/// }
+///
+/// let video_stream = dextreamer::VideoStreamBuilder::new("file:///home/user/my_video.mkv")
+///     .frame_handler_mut(FirstFrames { remaining: 10 })
+///     .open()
+///     .expect("to open the video");
+/// ```
+pub trait FrameHandlerMut: Send {
+    type Error: Display;
+
+    fn handle_new_frame(&mut self, frame: &VideoFrameRef<'_>) -> Result<FrameControl, Self::Error>;
+}
+
+/// `HandlerId` identifies a frame handler added with `VideoStream::add_frame_handler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerId(pub(crate) u64);
+
+/// `HandlerOptions` configures the frames passed to a frame handler added with
+/// `VideoStream::add_frame_handler`. Every added handler gets its own branch of the pipeline, so
+/// it can receive the frames in another format or size than the other handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandlerOptions {
+    pub(crate) pixel_formats: Vec<PixelFormat>,
+    pub(crate) output_size: OutputSize,
+}
+
+impl Default for HandlerOptions {
+    fn default() -> Self {
+        Self {
+            pixel_formats: vec![PixelFormat::default()],
+            output_size: OutputSize::default(),
+        }
+    }
+}
+
+impl HandlerOptions {
+    /// Sets the pixel format of the frames. Defaults to `PixelFormat::Rgba`.
+    pub fn pixel_format(self, pixel_format: PixelFormat) -> Self {
+        self.pixel_formats([pixel_format])
+    }
+
+    /// Sets the pixel formats accepted for the frames, in the order of preference. An empty list
+    /// is ignored.
+    pub fn pixel_formats(mut self, pixel_formats: impl IntoIterator<Item = PixelFormat>) -> Self {
+        let pixel_formats: Vec<PixelFormat> = pixel_formats.into_iter().collect();
+        if !pixel_formats.is_empty() {
+            self.pixel_formats = pixel_formats;
+        }
+        self
+    }
+
+    /// Sets the size of the frames. Defaults to `OutputSize::Source`.
+    pub fn output_size(mut self, output_size: OutputSize) -> Self {
+        self.output_size = output_size;
+        self
+    }
+}
+
+/// Both kinds of frame handlers, with the error type erased so that they can be boxed.
+pub(crate) trait AnyFrameHandler: Send {
+    fn handle_new_frame(
+        &mut self,
+        frame: &VideoFrameRef<'_>,
+    ) -> Result<FrameControl, DextreamerError>;
+}
+
+pub(crate) struct Infallible<H>(pub(crate) H);
+
+impl<H: FrameHandler> AnyFrameHandler for Infallible<H> {
+    fn handle_new_frame(
+        &mut self,
+        frame: &VideoFrameRef<'_>,
+    ) -> Result<FrameControl, DextreamerError> {
+        self.0.handle_new_frame(frame);
+        Ok(FrameControl::Continue)
+    }
+}
+
+pub(crate) struct Fallible<H>(pub(crate) H);
+
+impl<H: FrameHandlerMut> AnyFrameHandler for Fallible<H> {
+    fn handle_new_frame(
+        &mut self,
+        frame: &VideoFrameRef<'_>,
+    ) -> Result<FrameControl, DextreamerError> {
+        self.0
+            .handle_new_frame(frame)
+            .map_err(|err| DextreamerError::FrameHandler(err.to_string()))
+    }
+}
+
+/// Carries out what a frame handler asked for. Errors are reported and pause the playback.
+///
+/// The control thread changes the state of the pipeline, it can't be done from the streaming
+/// thread.
+pub(crate) fn apply_frame_control(
+    result: Result<FrameControl, DextreamerError>,
+    internal_sender: &Sender<InternalMessage>,
+    external_sender: &EventSender,
+) -> Result<(), DextreamerError> {
+    let action = match result {
+        Ok(FrameControl::Continue) => return Ok(()),
+        Ok(FrameControl::Pause) => VideoStreamAction::SetPause,
+        Ok(FrameControl::Close) => VideoStreamAction::Close,
+        Err(err) => {
+            external_sender.send(VideoStreamEvent::Error(err))?;
+            VideoStreamAction::SetPause
+        }
+    };
+
+    internal_sender
+        .send(InternalMessage::VideoStreamAction(action))
+        .map_err(|_| DextreamerError::ChannelDisconnected)
 }
@@ -0,0 +1,420 @@
+use std::sync::{Arc, MutexGuard};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::channel::{Channel, ChannelState};
+use crate::FrameData;
+
+/// `DropPolicy` selects what happens when a frame is decoded while the frame queue is full.
+///
+/// # Variants
+///
+/// * `DropOldest`: Discard the oldest queued frame to make room for the new one. Decoding never waits for the receiver.
+/// * `Block`: Wait until the receiver takes a frame. No frame is lost, but decoding, and therefore seeking, stalls while the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Discard the oldest queued frame to make room for the new one. Decoding never waits for the
+    /// receiver.
+    #[default]
+    DropOldest,
+    /// Wait until the receiver takes a frame. No frame is lost, but decoding, and therefore
+    /// seeking, stalls while the queue is full.
+    Block,
+}
+
+/// The state of a frame queue. The extra state holds the frames that are no longer used, whose
+/// storage is reused for the next frames.
+type QueueState<T> = ChannelState<T, Vec<T>>;
+
+/// A bounded queue of frames, shared between the thread producing them and the one consuming
+/// them. It is closed when either end is gone, after which nothing is queued anymore.
+pub(crate) struct FrameQueue<T> {
+    channel: Channel<T, Vec<T>>,
+    depth: usize,
+    drop_policy: DropPolicy,
+    /// The number of unused frames kept for reuse.
+    pool_size: usize,
+    /// Called for every frame dropped to make room for a newer one.
+    on_dropped: Option<Box<dyn Fn() + Send + Sync>>,
+}
+
+impl<T> FrameQueue<T> {
+    fn new(
+        depth: usize,
+        drop_policy: DropPolicy,
+        pool_size: usize,
+        on_dropped: Option<Box<dyn Fn() + Send + Sync>>,
+    ) -> Self {
+        Self {
+            channel: Channel::new(Vec::new()),
+            depth: depth.max(1),
+            drop_policy,
+            pool_size,
+            on_dropped,
+        }
+    }
+
+    /// Creates a queue holding at most `depth` frames, which drops the oldest one when it is full
+    /// and calls `on_dropped` for every dropped frame. The frames are not reused.
+    pub(crate) fn dropping_oldest(
+        depth: usize,
+        on_dropped: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(depth, DropPolicy::DropOldest, 0, Some(Box::new(on_dropped)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<T>> {
+        self.channel.lock()
+    }
+
+    /// Queues a frame, making room for it according to the drop policy. Returns `false` if the
+    /// queue has been closed.
+    pub(crate) fn push(&self, frame: T) -> bool {
+        let mut state = self.lock();
+        let mut evicted = None;
+        while state.items.len() >= self.depth && !state.closed {
+            match self.drop_policy {
+                DropPolicy::DropOldest => {
+                    // The queue never holds more than `depth` frames, so this runs at most once
+                    if let Some(oldest) = state.items.pop_front() {
+                        evicted = self.recycle(&mut state, oldest);
+                        if let Some(on_dropped) = &self.on_dropped {
+                            on_dropped();
+                        }
+                    }
+                }
+                DropPolicy::Block => state = self.channel.wait_for_room(state),
+            }
+        }
+
+        let is_queued = !state.closed;
+        if is_queued {
+            self.channel.push(&mut state, frame);
+        }
+        drop(state);
+
+        // Dropped outside of the lock, dropping a frame can release pipeline resources
+        drop(evicted);
+
+        is_queued
+    }
+
+    /// Blocks until a frame is queued. Returns `None` once the queue is closed and all the queued
+    /// frames have been taken.
+    pub(crate) fn recv(&self) -> Option<T> {
+        self.channel.recv()
+    }
+
+    pub(crate) fn close(&self) {
+        let mut state = self.lock();
+        self.channel.close(&mut state);
+    }
+
+    /// Closes the queue and drops the frames it holds.
+    pub(crate) fn close_and_clear(&self) {
+        let frames = {
+            let mut state = self.lock();
+            self.channel.close(&mut state);
+            std::mem::take(&mut state.items)
+        };
+
+        // Dropped outside of the lock, dropping a frame can release pipeline resources
+        drop(frames);
+    }
+
+    /// Keeps a frame that is no longer used for reuse if the pool has room for it. Returns the
+    /// frame otherwise, so that it can be dropped once the lock is released.
+    fn recycle(&self, state: &mut QueueState<T>, frame: T) -> Option<T> {
+        if state.extra.len() < self.pool_size {
+            state.extra.push(frame);
+            None
+        } else {
+            Some(frame)
+        }
+    }
+}
+
+/// Creates a frame queue holding at most `depth` frames and returns its two ends.
+pub(crate) fn frame_queue(depth: usize, drop_policy: DropPolicy) -> (FrameSender, FrameReceiver) {
+    // Enough frames to cover a full queue plus the frames held by the receiver
+    let pool_size = depth.max(1) + 2;
+    let queue = Arc::new(FrameQueue::new(depth, drop_policy, pool_size, None));
+
+    (
+        FrameSender {
+            queue: queue.clone(),
+        },
+        FrameReceiver { queue },
+    )
+}
+
+/// The end of the frame queue used by the video sink.
+pub(crate) struct FrameSender {
+    queue: Arc<FrameQueue<FrameData>>,
+}
+
+impl FrameSender {
+    /// Queues a frame written into storage taken from the pool. Returns `false` if the queue has
+    /// been closed.
+    pub(crate) fn send_with(&self, write: impl FnOnce(&mut FrameData)) -> bool {
+        let mut frame = {
+            let mut state = self.queue.lock();
+            if state.closed {
+                return false;
+            }
+            state.extra.pop().unwrap_or_default()
+        };
+
+        // Copy outside of the lock, the receiver can keep taking frames meanwhile
+        write(&mut frame);
+
+        self.queue.push(frame)
+    }
+
+    /// Returns a handle that closes the queue from another thread.
+    pub(crate) fn closer(&self) -> FrameQueueCloser {
+        FrameQueueCloser {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl Drop for FrameSender {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// Closes the frame queue when the stream shuts down, so that the streaming thread doesn't stay
+/// blocked on a full queue while the pipeline is being stopped.
+pub(crate) struct FrameQueueCloser {
+    queue: Arc<FrameQueue<FrameData>>,
+}
+
+impl FrameQueueCloser {
+    pub(crate) fn close(&self) {
+        self.queue.close();
+    }
+}
+
+/// `FrameReceiver` receives owned copies of the decoded frames, as enabled with
+/// `VideoStreamBuilder::frame_queue` and taken with `VideoStream::take_frame_receiver`.
+///
+/// The storage of the frames comes from a pool: passing the frames back with
+/// [`FrameReceiver::recycle`] once they are no longer needed avoids allocating memory for every
+/// frame. Frames that are simply dropped are freed instead.
+///
+/// The receiver can be moved to another thread. Dropping it stops the delivery of frames.
+pub struct FrameReceiver {
+    queue: Arc<FrameQueue<FrameData>>,
+}
+
+impl FrameReceiver {
+    /// Blocks until the next frame is decoded. Returns `None` once the stream is closed and all
+    /// the queued frames have been received.
+    pub fn recv(&self) -> Option<FrameData> {
+        self.queue.recv()
+    }
+
+    /// Returns the next frame if there is one, without blocking.
+    pub fn try_recv(&self) -> Option<FrameData> {
+        self.queue.channel.try_recv()
+    }
+
+    /// Waits for the next frame for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<FrameData> {
+        self.queue.channel.recv_timeout(timeout)
+    }
+
+    /// Returns the next frame if there is one, or registers the task to be woken up by the next
+    /// frame otherwise.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<FrameData>> {
+        self.queue.channel.poll_recv(cx)
+    }
+
+    /// Hands a frame back so that its storage is reused for the next frames.
+    pub fn recycle(&self, frame: FrameData) {
+        let mut state = self.queue.lock();
+        let rejected = self.queue.recycle(&mut state, frame);
+        drop(state);
+
+        drop(rejected);
+    }
+}
+
+impl Drop for FrameReceiver {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+
+    fn send_frame(sender: &FrameSender, index: u64) -> bool {
+        sender.send_with(|frame| {
+            frame.index = index;
+            frame.data.resize(1024, 0);
+        })
+    }
+
+    fn pool_len(receiver: &FrameReceiver) -> usize {
+        receiver.queue.lock().extra.len()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_newest_frames() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::DropOldest);
+
+        for index in 0..5 {
+            assert!(send_frame(&sender, index));
+        }
+
+        assert_eq!(receiver.try_recv().map(|frame| frame.index), Some(3));
+        assert_eq!(receiver.try_recv().map(|frame| frame.index), Some(4));
+        assert!(receiver.try_recv().is_none());
+    }
+
+    #[test]
+    fn block_waits_for_receiver() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::Block);
+
+        let sender_thread = thread::spawn(move || {
+            for index in 0..10 {
+                assert!(send_frame(&sender, index));
+            }
+        });
+
+        // Every frame is received, in order
+        for index in 0..10 {
+            assert_eq!(receiver.recv().map(|frame| frame.index), Some(index));
+        }
+        sender_thread.join().unwrap();
+        assert!(receiver.recv().is_none());
+    }
+
+    #[test]
+    fn closing_unblocks_sender() {
+        let (sender, receiver) = frame_queue(1, DropPolicy::Block);
+        let closer = sender.closer();
+        assert!(send_frame(&sender, 0));
+
+        let sender_thread = thread::spawn(move || send_frame(&sender, 1));
+        thread::sleep(Duration::from_millis(50));
+        closer.close();
+
+        assert!(!sender_thread.join().unwrap());
+        drop(receiver);
+    }
+
+    #[test]
+    fn closing_unblocks_receiver() {
+        let (sender, receiver) = frame_queue(1, DropPolicy::DropOldest);
+
+        let receiver_thread = thread::spawn(move || receiver.recv().is_none());
+        thread::sleep(Duration::from_millis(50));
+        drop(sender);
+
+        assert!(receiver_thread.join().unwrap());
+    }
+
+    #[test]
+    fn queued_frames_are_received_after_close() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::DropOldest);
+        assert!(send_frame(&sender, 0));
+        drop(sender);
+
+        assert_eq!(receiver.recv().map(|frame| frame.index), Some(0));
+        assert!(receiver.recv().is_none());
+    }
+
+    #[test]
+    fn dropping_oldest_counts_evicted_frames() {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let queue = {
+            let dropped = dropped.clone();
+            FrameQueue::dropping_oldest(2, move || {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+
+        for index in 0..5u64 {
+            assert!(queue.push(index));
+        }
+
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(queue.recv(), Some(3));
+        assert_eq!(queue.recv(), Some(4));
+
+        // Taking frames makes room, nothing is dropped until the queue is full again
+        assert!(queue.push(5));
+        assert!(queue.push(6));
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert!(queue.push(7));
+        assert_eq!(dropped.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn pool_is_capped() {
+        let depth = 2;
+        let (_sender, receiver) = frame_queue(depth, DropPolicy::DropOldest);
+
+        for _ in 0..10 {
+            receiver.recycle(FrameData::default());
+        }
+
+        assert_eq!(pool_len(&receiver), depth + 2);
+    }
+
+    #[test]
+    fn recycled_frames_are_reused() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::DropOldest);
+
+        assert!(send_frame(&sender, 0));
+        let frame = receiver.recv().unwrap();
+        let data_ptr = frame.data.as_ptr();
+        receiver.recycle(frame);
+
+        assert!(send_frame(&sender, 1));
+        assert_eq!(receiver.recv().unwrap().data.as_ptr(), data_ptr);
+    }
+
+    #[test]
+    fn steady_state_does_not_allocate() {
+        let (sender, receiver) = frame_queue(2, DropPolicy::DropOldest);
+
+        // Fill the pool, then only the storage from the pool is used
+        for index in 0..4 {
+            assert!(send_frame(&sender, index));
+        }
+        while let Some(frame) = receiver.try_recv() {
+            receiver.recycle(frame);
+        }
+
+        let pooled: HashSet<_> = receiver
+            .queue
+            .lock()
+            .extra
+            .iter()
+            .map(|frame| frame.data.as_ptr())
+            .collect();
+
+        for index in 0..100 {
+            assert!(send_frame(&sender, index));
+            if index % 3 == 0 {
+                // Let the queue overflow now and then, the dropped frames go back to the pool
+                continue;
+            }
+            while let Some(frame) = receiver.try_recv() {
+                assert!(pooled.contains(&frame.data.as_ptr()));
+                receiver.recycle(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::events::EventSender;
+use crate::frame_handler::{apply_frame_control, AnyFrameHandler};
+use crate::frame_queue::FrameQueue;
+use crate::statistics::FrameCounters;
+use crate::streamer::InternalMessage;
+use crate::Frame;
+
+/// The end of the worker queue used by the video sink.
+pub(crate) struct WorkerSender {
+    queue: Arc<FrameQueue<Frame>>,
+}
+
+impl WorkerSender {
+    /// Hands a frame to the worker thread, dropping the oldest queued frame if the queue is full.
+    pub(crate) fn submit(&self, frame: Frame) {
+        self.queue.push(frame);
+    }
+}
+
+/// `HandlerWorker` runs the frame handler on a thread of its own, fed with the frames decoded by
+/// the video sink through a small queue. A slow handler only causes frames to be dropped, rather
+/// than stalling the pipeline.
+pub(crate) struct HandlerWorker {
+    queue: Arc<FrameQueue<Frame>>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl HandlerWorker {
+    /// Spawns the worker thread and returns the worker along with the end of its queue used by
+    /// the video sink.
+    pub(crate) fn spawn(
+        mut frame_handler: Box<dyn AnyFrameHandler>,
+        depth: usize,
+        counters: Arc<FrameCounters>,
+        internal_sender: Sender<InternalMessage>,
+        external_sender: EventSender,
+    ) -> (Self, WorkerSender) {
+        // Dropping a frame releases its buffer right away, so that the decoder can reuse it
+        let queue = Arc::new(FrameQueue::<Frame>::dropping_oldest(depth, move || {
+            counters.handler_dropped.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let thread_handle = {
+            let queue = queue.clone();
+
+            thread::spawn(move || {
+                while let Some(frame) = queue.recv() {
+                    let result = frame_handler.handle_new_frame(&frame.as_frame_ref());
+                    // Releases the buffer before waiting for the next frame
+                    drop(frame);
+
+                    if apply_frame_control(result, &internal_sender, &external_sender).is_err() {
+                        break;
+                    }
+                }
+            })
+        };
+
+        let worker = Self {
+            queue: queue.clone(),
+            thread_handle: Some(thread_handle),
+        };
+
+        (worker, WorkerSender { queue })
+    }
+
+    /// Drops the queued frames and waits for the frame being handled, if any.
+    pub(crate) fn stop(&mut self) {
+        let Some(thread_handle) = self.thread_handle.take() else {
+            return;
+        };
+
+        self.queue.close_and_clear();
+
+        // The worker only panics if the frame handler does, there is nothing left to clean up
+        let _ = thread_handle.join();
+    }
+}
+
+impl Drop for HandlerWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
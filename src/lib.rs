@@ -14,26 +14,26 @@
 //! Here is an example of how to open a video:
 //!
 //! ```no_run
-//! use std::sync::mpsc;
 //! use dextreamer;
 //!
 //! struct VideoFrameLoader;
 //!
 //! impl dextreamer::FrameHandler for VideoFrameLoader {
-//!     fn handle_new_frame(&self, frame_data: &[u8], frame_size: (u32, u32)) {
-//!         println!("New frame: {:?}", frame_size);
+//!     fn handle_new_frame(&self, frame: &dextreamer::VideoFrameRef<'_>) {
+//!         println!("New frame: {}x{}", frame.width(), frame.height());
 //!     }
 //!
 //! }
 //!
 //! // open a video
-//! let (sender, receiver) = dextreamer::open_video("file:///home/user/my_video.mkv", VideoFrameLoader);
+//! let video_stream = dextreamer::open_video("file:///home/user/my_video.mkv", VideoFrameLoader)
+//!     .expect("to open the video");
 //!
 //! // Send a play action to the video thread
-//! sender.send(dextreamer::VideoStreamAction::SetPlay).unwrap();
+//! video_stream.play().unwrap();
 //!
 //! // Receive events from the video thread
-//! match receiver.recv().unwrap() {
+//! match video_stream.recv_event().unwrap() {
 //!     dextreamer::VideoStreamEvent::VideoLoaded(info) => println!("Video loaded: {:?}", info),
 //!     dextreamer::VideoStreamEvent::NewFrame => println!("New frame"),
 //!     _ => (),
@@ -48,12 +48,48 @@
 //! This library is distributed under the terms of the MIT license.
 //! See [LICENSE](LICENSE) for details.
 
+#[cfg(feature = "async")]
+mod async_stream;
+mod builder;
+mod bus;
+mod channel;
+#[cfg(feature = "async")]
+mod completion;
+mod controller;
+mod error;
+mod events;
+mod frame;
 mod frame_handler;
+mod frame_queue;
+mod handler_worker;
+mod loop_mode;
+mod mailbox;
+mod output_size;
+mod pixel_format;
 mod playbin_query;
+mod seek;
+mod statistics;
 mod streamer;
 mod video_info;
 mod video_sink;
+mod video_stream;
 
+#[cfg(feature = "async")]
+pub use async_stream::{EventStream, FrameStream};
+pub use builder::{AudioSink, VideoStreamBuilder};
+#[cfg(feature = "async")]
+pub use completion::Completion;
+pub use error::*;
+pub use events::EventMask;
+pub use frame::{Frame, VideoFrameRef};
 pub use frame_handler::*;
+pub use frame_queue::{DropPolicy, FrameReceiver};
+pub use loop_mode::LoopMode;
+pub use mailbox::{FrameMailbox, LatestFrame};
+pub use output_size::*;
+pub use pixel_format::*;
+pub use seek::{SeekMode, SeekTarget};
+pub use statistics::StreamStatistics;
 pub use streamer::*;
 pub use video_info::*;
+pub use video_stream::*;
@@ -0,0 +1,236 @@
+/// `LoopMode` selects which part of the media is played over and over, see
+/// `VideoStreamAction::SetLoop`.
+///
+/// # Variants
+///
+/// * `Off`: Play the media once, and stop at its end.
+/// * `File`: Restart from the beginning each time the end of the media is reached.
+/// * `Range { start, end }`: Play the part between `start` and `end`, in seconds, over and over.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LoopMode {
+    /// Play the media once, and stop at its end.
+    #[default]
+    Off,
+    /// Restart from the beginning each time the end of the media is reached.
+    File,
+    /// Play the part between `start` and `end`, in seconds, over and over.
+    Range { start: f64, end: f64 },
+}
+
+impl LoopMode {
+    /// Returns the start and the end of the looped part in seconds, the end being `None` for the
+    /// end of the media. Ranges are clamped to the media if its duration is known. Returns `None`
+    /// if the playback is not looped.
+    pub(crate) fn bounds(self, duration: f64) -> Option<(f64, Option<f64>)> {
+        match self {
+            LoopMode::Off => None,
+            LoopMode::File => Some((0.0, None)),
+            LoopMode::Range { start, end } => {
+                let (start, end) = (start.min(end).max(0.0), start.max(end));
+                let end = if duration > 0.0 {
+                    end.min(duration)
+                } else {
+                    end
+                };
+                Some((start, Some(end)))
+            }
+        }
+    }
+
+    /// Whether the loop can be applied to the media, whose duration is 0.0 if it is unknown.
+    pub(crate) fn is_valid(self, duration: f64) -> bool {
+        match self {
+            LoopMode::Off | LoopMode::File => true,
+            // The range has to overlap the media
+            LoopMode::Range { start, end } => {
+                start.is_finite()
+                    && end.is_finite()
+                    && start != end
+                    && start.max(end) > 0.0
+                    && (duration <= 0.0 || start.min(end) < duration)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_of_ranges() {
+        // (loop mode, duration, expected bounds)
+        let cases = [
+            (LoopMode::Off, 10.0, None),
+            (LoopMode::File, 10.0, Some((0.0, None))),
+            (
+                LoopMode::Range {
+                    start: 2.0,
+                    end: 5.0,
+                },
+                10.0,
+                Some((2.0, Some(5.0))),
+            ),
+            // Reversed ranges are swapped
+            (
+                LoopMode::Range {
+                    start: 5.0,
+                    end: 2.0,
+                },
+                10.0,
+                Some((2.0, Some(5.0))),
+            ),
+            // Negative starts are clamped to the start of the media
+            (
+                LoopMode::Range {
+                    start: -3.0,
+                    end: 4.0,
+                },
+                10.0,
+                Some((0.0, Some(4.0))),
+            ),
+            (
+                LoopMode::Range {
+                    start: 4.0,
+                    end: -3.0,
+                },
+                10.0,
+                Some((0.0, Some(4.0))),
+            ),
+            // Ranges past the end of the media are clamped to it, unless the duration is unknown
+            (
+                LoopMode::Range {
+                    start: 2.0,
+                    end: 1e11,
+                },
+                10.0,
+                Some((2.0, Some(10.0))),
+            ),
+            (
+                LoopMode::Range {
+                    start: 2.0,
+                    end: 1e11,
+                },
+                0.0,
+                Some((2.0, Some(1e11))),
+            ),
+        ];
+
+        for (loop_mode, duration, expected) in cases {
+            assert_eq!(
+                loop_mode.bounds(duration),
+                expected,
+                "{loop_mode:?} with a duration of {duration}"
+            );
+        }
+    }
+
+    #[test]
+    fn validity_of_ranges() {
+        // (loop mode, duration, expected validity)
+        let cases = [
+            (LoopMode::Off, 10.0, true),
+            (LoopMode::File, 10.0, true),
+            (
+                LoopMode::Range {
+                    start: 2.0,
+                    end: 5.0,
+                },
+                10.0,
+                true,
+            ),
+            (
+                LoopMode::Range {
+                    start: 5.0,
+                    end: 2.0,
+                },
+                10.0,
+                true,
+            ),
+            (
+                LoopMode::Range {
+                    start: -1.0,
+                    end: 1.0,
+                },
+                10.0,
+                true,
+            ),
+            // Empty ranges
+            (
+                LoopMode::Range {
+                    start: 3.0,
+                    end: 3.0,
+                },
+                10.0,
+                false,
+            ),
+            // Ranges before the start of the media
+            (
+                LoopMode::Range {
+                    start: -5.0,
+                    end: -1.0,
+                },
+                10.0,
+                false,
+            ),
+            (
+                LoopMode::Range {
+                    start: -1.0,
+                    end: 0.0,
+                },
+                10.0,
+                false,
+            ),
+            // Non-finite ranges
+            (
+                LoopMode::Range {
+                    start: 0.0,
+                    end: f64::INFINITY,
+                },
+                10.0,
+                false,
+            ),
+            (
+                LoopMode::Range {
+                    start: f64::NAN,
+                    end: 5.0,
+                },
+                10.0,
+                false,
+            ),
+            (
+                LoopMode::Range {
+                    start: f64::NEG_INFINITY,
+                    end: 5.0,
+                },
+                10.0,
+                false,
+            ),
+            // Ranges past the end of the media, unless the duration is unknown
+            (
+                LoopMode::Range {
+                    start: 20.0,
+                    end: 30.0,
+                },
+                10.0,
+                false,
+            ),
+            (
+                LoopMode::Range {
+                    start: 20.0,
+                    end: 30.0,
+                },
+                0.0,
+                true,
+            ),
+        ];
+
+        for (loop_mode, duration, expected) in cases {
+            assert_eq!(
+                loop_mode.is_valid(duration),
+                expected,
+                "{loop_mode:?} with a duration of {duration}"
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::FrameData;
+
+/// Set on the shared slot index when the slot holds a frame the reader hasn't seen yet.
+const FRESH_BIT: usize = 0b100;
+const INDEX_MASK: usize = 0b011;
+
+/// A frame stored in the mailbox, along with the number of frames written before it.
+#[derive(Default)]
+struct Slot {
+    generation: u64,
+    frame: FrameData,
+}
+
+/// A triple buffer: the writer and the reader each own one of the slots, and the third one is
+/// exchanged between them with a single atomic swap. Neither side ever waits for the other.
+struct Mailbox {
+    slots: [UnsafeCell<Slot>; 3],
+    /// The index of the slot that is owned by neither side, with `FRESH_BIT` set if it has been
+    /// written since the reader last took it.
+    shared_index: AtomicUsize,
+}
+
+// SAFETY: a slot is only ever accessed by the side that currently owns its index, and the
+//  ownership of the indices is transferred with acquire-release swaps of `shared_index`.
+unsafe impl Sync for Mailbox {}
+
+/// Creates a mailbox and returns its two ends.
+pub(crate) fn frame_mailbox() -> (MailboxWriter, FrameMailbox) {
+    let mailbox = Arc::new(Mailbox {
+        slots: Default::default(),
+        shared_index: AtomicUsize::new(1),
+    });
+
+    let writer = MailboxWriter {
+        mailbox: mailbox.clone(),
+        index: 0,
+        generation: 0,
+    };
+    let reader = FrameMailbox { mailbox, index: 2 };
+
+    (writer, reader)
+}
+
+/// The end of the mailbox used by the video sink.
+pub(crate) struct MailboxWriter {
+    mailbox: Arc<Mailbox>,
+    index: usize,
+    generation: u64,
+}
+
+impl MailboxWriter {
+    /// Writes a frame into the mailbox, replacing the previous one if it hasn't been read yet.
+    /// The storage of the frames is reused.
+    pub(crate) fn publish(&mut self, write: impl FnOnce(&mut FrameData)) {
+        self.generation += 1;
+
+        // SAFETY: the writer owns the slot at `self.index`
+        let slot = unsafe { &mut *self.mailbox.slots[self.index].get() };
+        slot.generation = self.generation;
+        write(&mut slot.frame);
+
+        let previous = self
+            .mailbox
+            .shared_index
+            .swap(self.index | FRESH_BIT, Ordering::AcqRel);
+        self.index = previous & INDEX_MASK;
+    }
+}
+
+/// `FrameMailbox` holds the newest decoded frame, as enabled with
+/// `VideoStreamBuilder::frame_mailbox` and taken with `VideoStream::take_frame_mailbox`.
+///
+/// Polling the mailbox never waits for the decoding, and the decoding never waits for it: frames
+/// that are not polled in time are skipped. The mailbox can be moved to a render thread.
+pub struct FrameMailbox {
+    mailbox: Arc<Mailbox>,
+    index: usize,
+}
+
+impl FrameMailbox {
+    /// Returns the newest frame, or `None` if no frame has been written yet.
+    ///
+    /// The generation of the returned frame can be compared with the one of the previous call to
+    /// skip frames that have already been rendered.
+    pub fn latest(&mut self) -> Option<LatestFrame<'_>> {
+        if self.mailbox.shared_index.load(Ordering::Relaxed) & FRESH_BIT != 0 {
+            let previous = self.mailbox.shared_index.swap(self.index, Ordering::AcqRel);
+            self.index = previous & INDEX_MASK;
+        }
+
+        // SAFETY: the reader owns the slot at `self.index`, and the returned reference borrows
+        //  the reader mutably, so the slot can't be handed back to the writer while it is alive.
+        let slot = unsafe { &*self.mailbox.slots[self.index].get() };
+
+        // Generations start at 1, the slot has never been written otherwise
+        (slot.generation > 0).then_some(LatestFrame {
+            generation: slot.generation,
+            frame: &slot.frame,
+        })
+    }
+}
+
+/// `LatestFrame` is the newest frame of the stream, as returned by `FrameMailbox::latest`.
+///
+/// # Fields
+///
+/// * `generation: u64`: Increased by one for every frame written into the mailbox. The frame hasn't changed if the generation is the same as the one of the previous call.
+/// * `frame: &FrameData`: The frame itself. Its presentation timestamp is in `FrameData::pts`.
+#[derive(Debug, Clone, Copy)]
+pub struct LatestFrame<'a> {
+    /// Increased by one for every frame written into the mailbox. The frame hasn't changed if
+    /// the generation is the same as the one of the previous call.
+    pub generation: u64,
+    /// The frame itself. Its presentation timestamp is in `FrameData::pts`.
+    pub frame: &'a FrameData,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn write_frame(writer: &mut MailboxWriter, index: u64) {
+        writer.publish(|frame| {
+            frame.index = index;
+            frame.data.clear();
+            frame.data.resize(64, index as u8);
+        });
+    }
+
+    #[test]
+    fn empty_mailbox_has_no_frame() {
+        let (_writer, mut reader) = frame_mailbox();
+
+        assert!(reader.latest().is_none());
+    }
+
+    #[test]
+    fn read_returns_newest_frame() {
+        let (mut writer, mut reader) = frame_mailbox();
+
+        for index in 1..=5 {
+            write_frame(&mut writer, index);
+        }
+
+        let latest = reader.latest().unwrap();
+        assert_eq!(latest.generation, 5);
+        assert_eq!(latest.frame.index, 5);
+        assert!(latest.frame.data.iter().all(|&byte| byte == 5));
+    }
+
+    #[test]
+    fn read_without_new_write_keeps_generation() {
+        let (mut writer, mut reader) = frame_mailbox();
+
+        write_frame(&mut writer, 1);
+        let first = reader.latest().unwrap().generation;
+        let second = reader.latest().unwrap().generation;
+        assert_eq!(first, second);
+
+        write_frame(&mut writer, 2);
+        assert_eq!(reader.latest().unwrap().generation, first + 1);
+    }
+
+    #[test]
+    fn concurrent_reads_never_tear_frames() {
+        const FRAMES: u64 = 100_000;
+        let (mut writer, mut reader) = frame_mailbox();
+
+        let writer_thread = thread::spawn(move || {
+            for index in 1..=FRAMES {
+                write_frame(&mut writer, index);
+            }
+        });
+
+        let mut last_generation = 0;
+        while last_generation < FRAMES {
+            let Some(latest) = reader.latest() else {
+                continue;
+            };
+
+            assert!(latest.generation >= last_generation);
+            assert_eq!(latest.frame.index, latest.generation);
+            let expected = latest.frame.index as u8;
+            assert!(latest.frame.data.iter().all(|&byte| byte == expected));
+            last_generation = latest.generation;
+        }
+
+        writer_thread.join().unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use gstreamer as gst;
+use gstreamer_video as gst_video;
+
+use crate::PixelFormat;
+
+/// `OutputSize` selects the size of the frames produced by the stream. The scaling is done by
+/// gstreamer before the frames reach the frame handler.
+///
+/// # Variants
+///
+/// * `Source`: Keep the size of the source video.
+/// * `Fixed { width, height }`: Scale to exactly `width`x`height`, stretching the picture if the aspect ratios differ.
+/// * `MaxBounds { width, height }`: Downscale to fit within `width`x`height`, preserving the aspect ratio. Smaller videos are left untouched.
+/// * `Fit { width, height }`: Scale to fit within `width`x`height`, preserving the aspect ratio, and fill the rest with black letterbox borders.
+/// * `Fill { width, height }`: Scale to cover `width`x`height`, preserving the aspect ratio, and crop what overflows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputSize {
+    /// Keep the size of the source video.
+    #[default]
+    Source,
+    /// Scale to exactly `width`x`height`, stretching the picture if the aspect ratios differ.
+    Fixed { width: u32, height: u32 },
+    /// Downscale to fit within `width`x`height`, preserving the aspect ratio. Smaller videos are left untouched.
+    MaxBounds { width: u32, height: u32 },
+    /// Scale to fit within `width`x`height`, preserving the aspect ratio, and fill the rest with black letterbox borders.
+    Fit { width: u32, height: u32 },
+    /// Scale to cover `width`x`height`, preserving the aspect ratio, and crop what overflows.
+    Fill { width: u32, height: u32 },
+}
+
+impl OutputSize {
+    /// Builds the caps requesting this output size in one of the given pixel formats.
+    pub(crate) fn to_caps(self, pixel_formats: &[PixelFormat]) -> gst::Caps {
+        let caps_builder = gst_video::VideoCapsBuilder::new().format_list(
+            pixel_formats
+                .iter()
+                .map(|pixel_format| pixel_format.to_video_format()),
+        );
+        let square_pixels = gst::Fraction::new(1, 1);
+
+        match self {
+            OutputSize::Source => caps_builder.build(),
+            OutputSize::Fixed { width, height }
+            | OutputSize::Fit { width, height }
+            | OutputSize::Fill { width, height } => caps_builder
+                .width(clamp_dimension(width))
+                .height(clamp_dimension(height))
+                .pixel_aspect_ratio(square_pixels)
+                .build(),
+            // videoscale picks the largest size within the ranges that keeps the aspect ratio
+            OutputSize::MaxBounds { width, height } => caps_builder
+                .width_range(1..=clamp_dimension(width))
+                .height_range(1..=clamp_dimension(height))
+                .pixel_aspect_ratio(square_pixels)
+                .build(),
+        }
+    }
+
+    /// Whether videoscale has to add borders to keep the aspect ratio.
+    pub(crate) fn adds_borders(self) -> bool {
+        matches!(self, OutputSize::Fit { .. } | OutputSize::Fill { .. })
+    }
+
+    /// The aspect ratio the frames have to be cropped to before scaling, if any.
+    pub(crate) fn crop_aspect_ratio(self) -> Option<gst::Fraction> {
+        match self {
+            OutputSize::Fill { width, height } => Some(gst::Fraction::new(
+                clamp_dimension(width),
+                clamp_dimension(height),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn clamp_dimension(dimension: u32) -> i32 {
+    dimension.clamp(1, i32::MAX as u32) as i32
+}
@@ -0,0 +1,87 @@
+use gstreamer_video as gst_video;
+
+/// `PixelFormat` represents the layout of the pixels in the frames produced by the stream.
+///
+/// Packed formats store all the pixels in a single plane, while planar formats split the
+/// luma (Y) and chroma (U, V) components into separate planes. Planar formats are usually what
+/// the decoders produce, so requesting them avoids a color conversion on the CPU.
+///
+/// # Variants
+///
+/// * `Rgba`: Every 4 bytes represent one pixel, in the red, green, blue, alpha order.
+/// * `Bgra`: Every 4 bytes represent one pixel, in the blue, green, red, alpha order.
+/// * `Rgbx`: Every 4 bytes represent one pixel, in the red, green, blue order followed by an unused byte.
+/// * `Bgrx`: Every 4 bytes represent one pixel, in the blue, green, red order followed by an unused byte.
+/// * `Rgb`: Every 3 bytes represent one pixel, in the red, green, blue order.
+/// * `Bgr`: Every 3 bytes represent one pixel, in the blue, green, red order.
+/// * `Nv12`: Two planes: a full resolution Y plane, and a half resolution plane of interleaved U and V samples.
+/// * `I420`: Three planes: a full resolution Y plane, and half resolution U and V planes.
+/// * `Gray8`: One plane where every byte represents the luma of one pixel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// Every 4 bytes represent one pixel, in the red, green, blue, alpha order.
+    #[default]
+    Rgba,
+    /// Every 4 bytes represent one pixel, in the blue, green, red, alpha order.
+    Bgra,
+    /// Every 4 bytes represent one pixel, in the red, green, blue order followed by an unused byte.
+    Rgbx,
+    /// Every 4 bytes represent one pixel, in the blue, green, red order followed by an unused byte.
+    Bgrx,
+    /// Every 3 bytes represent one pixel, in the red, green, blue order.
+    Rgb,
+    /// Every 3 bytes represent one pixel, in the blue, green, red order.
+    Bgr,
+    /// Two planes: a full resolution Y plane, and a half resolution plane of interleaved U and V samples.
+    Nv12,
+    /// Three planes: a full resolution Y plane, and half resolution U and V planes.
+    I420,
+    /// One plane where every byte represents the luma of one pixel.
+    Gray8,
+}
+
+impl PixelFormat {
+    /// Returns the number of planes the pixels of this format are stored in.
+    pub fn plane_count(self) -> usize {
+        match self {
+            PixelFormat::Rgba
+            | PixelFormat::Bgra
+            | PixelFormat::Rgbx
+            | PixelFormat::Bgrx
+            | PixelFormat::Rgb
+            | PixelFormat::Bgr
+            | PixelFormat::Gray8 => 1,
+            PixelFormat::Nv12 => 2,
+            PixelFormat::I420 => 3,
+        }
+    }
+
+    pub(crate) fn to_video_format(self) -> gst_video::VideoFormat {
+        match self {
+            PixelFormat::Rgba => gst_video::VideoFormat::Rgba,
+            PixelFormat::Bgra => gst_video::VideoFormat::Bgra,
+            PixelFormat::Rgbx => gst_video::VideoFormat::Rgbx,
+            PixelFormat::Bgrx => gst_video::VideoFormat::Bgrx,
+            PixelFormat::Rgb => gst_video::VideoFormat::Rgb,
+            PixelFormat::Bgr => gst_video::VideoFormat::Bgr,
+            PixelFormat::Nv12 => gst_video::VideoFormat::Nv12,
+            PixelFormat::I420 => gst_video::VideoFormat::I420,
+            PixelFormat::Gray8 => gst_video::VideoFormat::Gray8,
+        }
+    }
+
+    pub(crate) fn from_video_format(video_format: gst_video::VideoFormat) -> Option<Self> {
+        match video_format {
+            gst_video::VideoFormat::Rgba => Some(PixelFormat::Rgba),
+            gst_video::VideoFormat::Bgra => Some(PixelFormat::Bgra),
+            gst_video::VideoFormat::Rgbx => Some(PixelFormat::Rgbx),
+            gst_video::VideoFormat::Bgrx => Some(PixelFormat::Bgrx),
+            gst_video::VideoFormat::Rgb => Some(PixelFormat::Rgb),
+            gst_video::VideoFormat::Bgr => Some(PixelFormat::Bgr),
+            gst_video::VideoFormat::Nv12 => Some(PixelFormat::Nv12),
+            gst_video::VideoFormat::I420 => Some(PixelFormat::I420),
+            gst_video::VideoFormat::Gray8 => Some(PixelFormat::Gray8),
+            _ => None,
+        }
+    }
+}
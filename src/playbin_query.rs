@@ -1,3 +1,4 @@
+use gst::glib;
 use gst::prelude::*;
 use gstreamer as gst;
 
@@ -5,6 +6,11 @@ use crate::{AudioTrack, SubtitleTrack};
 
 use gstreamer::Element;
 
+/// Nick of the playbin flag that enables subtitles rendering.
+pub(crate) const PLAYBIN_FLAG_TEXT: &str = "text";
+/// Nick of the playbin flag that enables audio output.
+pub(crate) const PLAYBIN_FLAG_AUDIO: &str = "audio";
+
 /// Retrieves the video info from the playbin pipeline.
 pub(crate) fn subtitle_tracks(playbin_pipeline: &Element) -> Vec<SubtitleTrack> {
     let subtitles = playbin_pipeline.property::<i32>("n-text");
@@ -69,6 +75,69 @@ pub(crate) fn audio_tracks(playbin_pipeline: &Element) -> Vec<AudioTrack> {
     audio_tracks
 }
 
+/// Retrieves the currently selected subtitle track, or `None` if subtitles are disabled.
+pub(crate) fn current_subtitle_track(playbin_pipeline: &Element) -> Option<usize> {
+    current_track(playbin_pipeline, "current-text", PLAYBIN_FLAG_TEXT)
+}
+
+/// Retrieves the currently selected audio track, or `None` if audio is disabled.
+pub(crate) fn current_audio_track(playbin_pipeline: &Element) -> Option<usize> {
+    current_track(playbin_pipeline, "current-audio", PLAYBIN_FLAG_AUDIO)
+}
+
+fn current_track(
+    playbin_pipeline: &Element,
+    property_name: &str,
+    flag_nick: &str,
+) -> Option<usize> {
+    let flags = playbin_pipeline.property_value("flags");
+    let is_enabled = glib::FlagsClass::new(flags.type_())
+        .is_some_and(|flags_class| flags_class.is_set_by_nick(&flags, flag_nick));
+
+    // playbin reports -1 when there are no tracks of that kind
+    let track = playbin_pipeline.property::<i32>(property_name);
+
+    if is_enabled {
+        usize::try_from(track).ok()
+    } else {
+        None
+    }
+}
+
+/// Turns one of the playbin `flags` on or off, leaving the other flags untouched.
+pub(crate) fn set_playbin_flag(playbin_pipeline: &Element, flag_nick: &str, enabled: bool) {
+    let flags = playbin_pipeline.property_value("flags");
+    let Some(flags_class) = glib::FlagsClass::new(flags.type_()) else {
+        return;
+    };
+
+    let flags = if enabled {
+        flags_class.set_by_nick(flags, flag_nick)
+    } else {
+        flags_class.unset_by_nick(flags, flag_nick)
+    };
+
+    if let Ok(flags) = flags {
+        playbin_pipeline.set_property_from_value("flags", &flags);
+    }
+}
+
+/// Retrieves the playback position in seconds, or `None` if it can't be queried yet, e.g.
+/// while the pipeline is prerolling after a seek.
+pub(crate) fn video_position(playbin_pipeline: &Element) -> Option<f64> {
+    let mut position_query = gst::query::Position::new(gst::Format::Time);
+
+    if !playbin_pipeline.query(&mut position_query) {
+        return None;
+    }
+    match position_query.result() {
+        gstreamer::GenericFormattedValue::Time(Some(position)) => {
+            Some(position.nseconds() as f64 / 1_000_000_000.0)
+        }
+        _ => None,
+    }
+}
+
 /// Retrieves the duration of the video stream.
 pub(crate) fn video_duration(playbin_pipeline: &Element) -> f64 {
     // Query the duration
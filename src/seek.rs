@@ -0,0 +1,222 @@
+use gstreamer as gst;
+
+/// The furthest position in seconds a seek can reach. Later positions don't fit in a
+/// `gst::ClockTime`, whose largest value stands for an unknown time.
+pub(crate) const MAX_SEEK_POSITION: f64 = (i64::MAX / 1_000_000_000) as f64;
+
+/// `SeekTarget` selects the position a `VideoStreamAction::Seek` moves to.
+///
+/// # Variants
+///
+/// * `Absolute(f64)`: Seek to the given position, in seconds.
+/// * `Relative(f64)`: Seek by the given offset from the current position, in seconds. Negative offsets seek backwards.
+/// * `Fraction(f64)`: Seek to the given fraction of the duration, between 0.0 and 1.0, e.g. from the position of a seek bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekTarget {
+    /// Seek to the given position, in seconds.
+    Absolute(f64),
+    /// Seek by the given offset from the current position, in seconds. Negative offsets seek backwards.
+    Relative(f64),
+    /// Seek to the given fraction of the duration, between 0.0 and 1.0, e.g. from the position of a seek bar.
+    Fraction(f64),
+}
+
+impl SeekTarget {
+    /// Resolves the target to a position in seconds, clamped to the media if its duration is
+    /// known.
+    pub(crate) fn position(self, current_position: f64, duration: f64) -> f64 {
+        let position = match self {
+            SeekTarget::Absolute(position) => position,
+            SeekTarget::Relative(offset) => current_position + offset,
+            SeekTarget::Fraction(fraction) => fraction.clamp(0.0, 1.0) * duration,
+        };
+
+        if duration > 0.0 {
+            position.clamp(0.0, duration)
+        } else {
+            position.max(0.0)
+        }
+    }
+
+    /// Resolves the target like `position`, or returns `None` if it can't be seeked to, e.g.
+    /// an infinite target while the duration is unknown.
+    pub(crate) fn checked_position(self, current_position: f64, duration: f64) -> Option<f64> {
+        let position = self.position(current_position, duration);
+        (position.is_finite() && position <= MAX_SEEK_POSITION).then_some(position)
+    }
+
+    /// Merges a seek queued behind this one into it, so that a single seek is executed. Relative
+    /// offsets are added to this target, other targets replace it. An offset queued behind a
+    /// fraction is ignored while the duration is unknown.
+    pub(crate) fn then(self, next: SeekTarget, duration: f64) -> SeekTarget {
+        match (self, next) {
+            (SeekTarget::Relative(offset), SeekTarget::Relative(next_offset)) => {
+                SeekTarget::Relative(offset + next_offset)
+            }
+            // The offset can't be added to a fraction of an unknown duration
+            (SeekTarget::Fraction(_), SeekTarget::Relative(_)) if duration <= 0.0 => self,
+            // Absolute and fractional targets don't depend on the current position
+            (target, SeekTarget::Relative(next_offset)) => {
+                SeekTarget::Absolute(target.position(0.0, duration) + next_offset)
+            }
+            (_, next) => next,
+        }
+    }
+}
+
+/// `SeekMode` trades the precision of a `VideoStreamAction::Seek` for its speed.
+///
+/// Seeking to a keyframe only decodes from that keyframe, while an accurate seek also decodes
+/// every frame between the keyframe and the target, which is slow on files with long GOPs. A
+/// seek bar would typically use keyframe seeks while it is dragged, and an accurate one once it
+/// is released.
+///
+/// # Variants
+///
+/// * `Accurate`: Seek to the exact target. The slowest mode.
+/// * `Keyframe`: Seek to the keyframe gstreamer finds most suitable for the target.
+/// * `SnapBefore`: Seek to the closest keyframe before the target.
+/// * `SnapAfter`: Seek to the closest keyframe after the target.
+/// * `SnapNearest`: Seek to the keyframe closest to the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeekMode {
+    /// Seek to the exact target. The slowest mode.
+    #[default]
+    Accurate,
+    /// Seek to the keyframe gstreamer finds most suitable for the target.
+    Keyframe,
+    /// Seek to the closest keyframe before the target.
+    SnapBefore,
+    /// Seek to the closest keyframe after the target.
+    SnapAfter,
+    /// Seek to the keyframe closest to the target.
+    SnapNearest,
+}
+
+impl SeekMode {
+    /// Returns the flags of a flushing seek in this mode.
+    pub(crate) fn seek_flags(self) -> gst::SeekFlags {
+        let mode_flags = match self {
+            SeekMode::Accurate => gst::SeekFlags::ACCURATE,
+            SeekMode::Keyframe => gst::SeekFlags::KEY_UNIT,
+            SeekMode::SnapBefore => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_BEFORE,
+            SeekMode::SnapAfter => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_AFTER,
+            SeekMode::SnapNearest => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_NEAREST,
+        };
+
+        gst::SeekFlags::FLUSH | mode_flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_resolves_targets() {
+        // (target, current position, duration, expected position)
+        let cases = [
+            (SeekTarget::Absolute(5.0), 2.0, 10.0, 5.0),
+            (SeekTarget::Absolute(15.0), 2.0, 10.0, 10.0),
+            (SeekTarget::Absolute(-1.0), 2.0, 10.0, 0.0),
+            (SeekTarget::Absolute(15.0), 2.0, 0.0, 15.0),
+            (SeekTarget::Relative(3.0), 2.0, 10.0, 5.0),
+            (SeekTarget::Relative(-3.0), 2.0, 10.0, 0.0),
+            (SeekTarget::Relative(20.0), 2.0, 10.0, 10.0),
+            (SeekTarget::Relative(20.0), 2.0, 0.0, 22.0),
+            (SeekTarget::Fraction(0.25), 2.0, 10.0, 2.5),
+            (SeekTarget::Fraction(1.5), 2.0, 10.0, 10.0),
+            (SeekTarget::Fraction(-0.5), 2.0, 10.0, 0.0),
+            (SeekTarget::Fraction(0.5), 2.0, 0.0, 0.0),
+        ];
+
+        for (target, current_position, duration, expected) in cases {
+            assert_eq!(
+                target.position(current_position, duration),
+                expected,
+                "{target:?} from {current_position} with a duration of {duration}"
+            );
+        }
+    }
+
+    #[test]
+    fn checked_position_rejects_unreachable_targets() {
+        assert_eq!(
+            SeekTarget::Absolute(5.0).checked_position(2.0, 0.0),
+            Some(5.0)
+        );
+        assert_eq!(
+            SeekTarget::Absolute(f64::INFINITY).checked_position(2.0, 10.0),
+            Some(10.0)
+        );
+        assert_eq!(
+            SeekTarget::Absolute(f64::INFINITY).checked_position(2.0, 0.0),
+            None
+        );
+        assert_eq!(
+            SeekTarget::Relative(f64::MAX).checked_position(2.0, 0.0),
+            None
+        );
+        assert_eq!(
+            SeekTarget::Fraction(f64::NAN).checked_position(2.0, 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn then_merges_targets() {
+        // (first target, next target, duration, merged target)
+        let cases = [
+            (
+                SeekTarget::Relative(10.0),
+                SeekTarget::Relative(10.0),
+                100.0,
+                SeekTarget::Relative(20.0),
+            ),
+            (
+                SeekTarget::Absolute(30.0),
+                SeekTarget::Relative(5.0),
+                100.0,
+                SeekTarget::Absolute(35.0),
+            ),
+            (
+                SeekTarget::Absolute(150.0),
+                SeekTarget::Relative(-10.0),
+                100.0,
+                SeekTarget::Absolute(90.0),
+            ),
+            (
+                SeekTarget::Fraction(0.5),
+                SeekTarget::Relative(5.0),
+                100.0,
+                SeekTarget::Absolute(55.0),
+            ),
+            (
+                SeekTarget::Fraction(0.5),
+                SeekTarget::Relative(5.0),
+                0.0,
+                SeekTarget::Fraction(0.5),
+            ),
+            (
+                SeekTarget::Relative(10.0),
+                SeekTarget::Absolute(20.0),
+                100.0,
+                SeekTarget::Absolute(20.0),
+            ),
+            (
+                SeekTarget::Absolute(20.0),
+                SeekTarget::Fraction(0.1),
+                0.0,
+                SeekTarget::Fraction(0.1),
+            ),
+        ];
+
+        for (first, next, duration, expected) in cases {
+            assert_eq!(
+                first.then(next, duration),
+                expected,
+                "{first:?} then {next:?} with a duration of {duration}"
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// `StreamStatistics` is a snapshot of the frame counters of a stream, as returned by
+/// `VideoStream::statistics`.
+///
+/// # Fields
+///
+/// * `frames_delivered: u64`: The number of frames passed to the frame outputs since the stream was opened.
+/// * `frames_dropped: u64`: The number of frames dropped before reaching the frame outputs, either because they were late or because the sink queue was full.
+/// * `handler_frames_dropped: u64`: The number of frames the frame handler skipped because it was still busy, when it runs on a thread of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStatistics {
+    /// The number of frames passed to the frame outputs since the stream was opened.
+    pub frames_delivered: u64,
+    /// The number of frames dropped before reaching the frame outputs, either because they were
+    /// late or because the sink queue was full.
+    pub frames_dropped: u64,
+    /// The number of frames the frame handler skipped because it was still busy, when it runs on
+    /// a thread of its own.
+    pub handler_frames_dropped: u64,
+}
+
+/// Counters updated from the streaming threads.
+#[derive(Default)]
+pub(crate) struct FrameCounters {
+    pub(crate) delivered: AtomicU64,
+    /// Frames dropped because the handler thread was still busy with the previous ones.
+    pub(crate) handler_dropped: AtomicU64,
+    /// Buffers the leaky sink queue dropped to make room for newer ones.
+    overflowed: AtomicU64,
+}
+
+/// Collects the statistics of the video sink. Shared between the sink bin and the `VideoStream`.
+#[derive(Clone)]
+pub(crate) struct SinkStatistics {
+    appsink: gst_app::AppSink,
+    counters: Arc<FrameCounters>,
+}
+
+impl SinkStatistics {
+    /// `leaky_queue` is the queue in front of the appsink, if one has been requested and drops
+    /// the oldest buffers when it is full.
+    pub(crate) fn new(
+        appsink: gst_app::AppSink,
+        leaky_queue: Option<&gst::Element>,
+        counters: Arc<FrameCounters>,
+    ) -> Self {
+        if let Some(queue) = leaky_queue {
+            count_overruns(queue, &counters);
+        }
+
+        Self { appsink, counters }
+    }
+
+    pub(crate) fn snapshot(&self) -> StreamStatistics {
+        let frames_delivered = self.counters.delivered.load(Ordering::Relaxed);
+
+        // Frames dropped by the appsink because they were too late to be displayed
+        let late_frames = self
+            .appsink
+            .property::<gst::Structure>("stats")
+            .get::<u64>("dropped")
+            .unwrap_or(0);
+
+        let overflowed_frames = self.counters.overflowed.load(Ordering::Relaxed);
+
+        StreamStatistics {
+            frames_delivered,
+            frames_dropped: late_frames + overflowed_frames,
+            handler_frames_dropped: self.counters.handler_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn count_overruns(queue: &gst::Element, counters: &Arc<FrameCounters>) {
+    let counters = counters.clone();
+
+    // A leaky queue signals an overrun for every incoming buffer that finds it full, right before
+    //  dropping its oldest buffer. Flushed buffers are not counted.
+    queue.connect("overrun", false, move |_| {
+        counters.overflowed.fetch_add(1, Ordering::Relaxed);
+        None
+    });
+}
@@ -1,19 +1,53 @@
 use gst::prelude::*;
 use gstreamer as gst;
 
-use crate::video_sink::memory_video_sink;
-use crate::{PlayingState, VideoInfo};
-
-use gstreamer::Bus;
-use std::sync::mpsc::{self, Receiver, Sender};
-
-use crate::frame_handler::FrameHandler;
-use crate::playbin_query::{audio_tracks, subtitle_tracks, video_duration};
+use crate::builder::StreamConfig;
+use crate::bus::{pop_pipeline_error, watch_bus};
+#[cfg(feature = "async")]
+use crate::completion::CompletionSender;
+use crate::controller::StreamController;
+use crate::events::event_channel;
+use crate::frame_queue::frame_queue;
+use crate::mailbox::frame_mailbox;
+use crate::video_sink::{memory_video_sink, FrameOutputs};
+use crate::{
+    AudioSink, DextreamerError, LoopMode, OutputSize, PixelFormat, PlayingState, SeekMode,
+    SeekTarget, VideoInfo, VideoStream, VideoStreamBuilder,
+};
+
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::frame_handler::{AnyFrameHandler, FrameHandler};
+use crate::playbin_query::{set_playbin_flag, PLAYBIN_FLAG_AUDIO, PLAYBIN_FLAG_TEXT};
 use std::thread;
 
 pub(crate) enum InternalMessage {
     VideoStreamAction(VideoStreamAction),
-    RequestPositionUpdate,
+    /// Sent by the bus thread once the media has been loaded.
+    VideoLoaded,
+    /// Sent by the bus thread if the media could not be loaded.
+    LoadFailed(DextreamerError),
+    /// Sent by the bus thread when the state of the pipeline itself has changed.
+    PipelineStateChanged,
+    /// Sent by the video sink with the position of the frame reached by
+    /// `VideoStreamAction::StepFrames`, in seconds.
+    FrameStepped(Option<f64>),
+    /// Sent by the bus thread when the pipeline has completed an asynchronous state change, e.g.
+    /// once it has prerolled after a seek.
+    AsyncDone,
+    /// Sent by the bus thread when the end of a segment seek has been reached, instead of the
+    /// end of stream.
+    SegmentDone,
+    /// Sent by the bus thread when the duration of the media may have changed.
+    DurationChanged,
+    /// Sent by the bus thread once it stops forwarding the messages, e.g. after a fatal error.
+    BusStopped,
+    /// Changes the playing state and completes once the pipeline has reached it.
+    #[cfg(feature = "async")]
+    AwaitPlayingState(PlayingState, CompletionSender),
 }
 
 /// `VideoStreamAction` represents the actions that can be sent to the video stream.
@@ -26,6 +60,11 @@ pub(crate) enum InternalMessage {
 /// * `SetPlay`: Start or resume playback.
 /// * `SetPause`: Pause playback.
 /// * `SeekToSeconds(f64)`: Seek to a specific position in the video, provided in seconds.
+/// * `Seek { target, mode }`: Seek to `target`, trading precision for speed according to `mode`.
+/// * `SetRate(f64)`: Change the playback rate, 1.0 being the normal speed. Negative rates play the video backwards.
+/// * `StepFrames(i32)`: Pause and move by the given number of frames, backwards if negative.
+/// * `SetLoop(LoopMode)`: Play the whole media or a part of it over and over, or stop looping.
+/// * `SetOutputSize(OutputSize)`: Change the size of the frames, e.g. when the window displaying them is resized.
 #[derive(Debug, Clone, Copy)]
 pub enum VideoStreamAction {
     /// Set the current subtitle track by its ID. If `None` is provided, the subtitles will be disabled.
@@ -41,39 +80,38 @@ pub enum VideoStreamAction {
     SetPause,
     /// Seek to a specific position in the video, provided in seconds.
     SeekToSeconds(f64),
+    /// Seek to `target`, trading precision for speed according to `mode`. `SeekToSeconds(seconds)`
+    /// is the same as an accurate seek to `SeekTarget::Absolute(seconds)`.
+    Seek { target: SeekTarget, mode: SeekMode },
+    /// Change the playback rate, 1.0 being the normal speed. Negative rates play the video
+    /// backwards. The pitch of the audio is preserved, which requires the `scaletempo` element
+    /// unless the audio is disabled.
+    SetRate(f64),
+    /// Pause and move by the given number of frames, backwards if negative. The frame reached is
+    /// delivered to the frame handler, and its position is reported with
+    /// `VideoStreamEvent::PositionChanged`. Ignored until the media is loaded.
+    StepFrames(i32),
+    /// Play the whole media or a part of it over and over, or stop looping. A range loop starts
+    /// at the beginning of the range. The loop wraps around without a gap, and is kept by the
+    /// seeks done meanwhile.
+    SetLoop(LoopMode),
+    /// Change the size of the frames, e.g. when the window displaying them is resized.
+    SetOutputSize(OutputSize),
     /// Close the media stream.
     Close,
 }
 
-// /// `VideoStreamActionType` represents the type of the action. This is used to debounce the actions.
-// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-// pub(crate) enum VideoStreamActionType {
-//     SetCurrentSubtitleTrack,
-//     SetCurrentAudioTrack,
-//     SetVolume,
-//     SetPlay,
-//     SetPause,
-//     SeekToSeconds,
-//     Close,
-// }
-
 impl VideoStreamAction {
-    // /// Returns the type of the action. This is used to debounce the actions.
-    // pub(crate) fn message_type(&self) -> VideoStreamActionType {
-    //     match self {
-    //         VideoStreamAction::SetCurrentSubtitleTrack(_) => {
-    //             VideoStreamActionType::SetCurrentSubtitleTrack
-    //         }
-    //         VideoStreamAction::SetCurrentAudioTrack(_) => {
-    //             VideoStreamActionType::SetCurrentAudioTrack
-    //         }
-    //         VideoStreamAction::SetVolume(_) => VideoStreamActionType::SetVolume,
-    //         VideoStreamAction::SetPlay => VideoStreamActionType::SetPlay,
-    //         VideoStreamAction::SetPause => VideoStreamActionType::SetPause,
-    //         VideoStreamAction::SeekToSeconds(_) => VideoStreamActionType::SeekToSeconds,
-    //         VideoStreamAction::Close => VideoStreamActionType::Close,
-    //     }
-    // }
+    /// Returns the target and the mode of a seek action.
+    pub(crate) fn seek_request(&self) -> Option<(SeekTarget, SeekMode)> {
+        match *self {
+            VideoStreamAction::SeekToSeconds(seconds) => {
+                Some((SeekTarget::Absolute(seconds), SeekMode::Accurate))
+            }
+            VideoStreamAction::Seek { target, mode } => Some((target, mode)),
+            _ => None,
+        }
+    }
 }
 
 /// `VideoStreamEvent` represents the events that can be emitted from the video stream.
@@ -81,309 +119,349 @@ impl VideoStreamAction {
 /// # Variants
 ///
 /// * `VideoLoaded(VideoInfo)`: Emitted when a video is successfully loaded. Contains metadata about the video.
-/// * `NewFrame(FrameData)`: Emitted for each new frame. Contains the raw data and size of the frame.
-/// * `CurrentAudioTrackChanged(Option<u32>)`: Emitted when the current audio track changes. Contains the new audio track ID.
-/// * `CurrentSubtitleTrackChanged(Option<u32>)`: Emitted when the current subtitle track changes. Contains the new subtitle track ID.
+/// * `LoadFailed(DextreamerError)`: Emitted instead of `VideoLoaded` when the media could not be loaded, e.g. because the file does not exist, its format is not supported or the load timed out.
+/// * `NewFrame`: Emitted for each new frame. To get the actual frame data, use the `FrameHandler` trait.
+/// * `Error(DextreamerError)`: Emitted when an error happens after the stream has been opened. Errors
+///   posted by the pipeline are reported as `DextreamerError::Pipeline`.
+/// * `CurrentAudioTrackChanged(Option<usize>)`: Emitted when the current audio track changes. Contains the new audio track ID, or `None` if the audio has been disabled.
+/// * `CurrentSubtitleTrackChanged(Option<usize>)`: Emitted when the current subtitle track changes. Contains the new subtitle track ID, or `None` if the subtitles have been disabled.
 /// * `VolumeChanged(f32)`: Emitted when the volume changes. Contains the new volume.
 /// * `PlayingStateChanged(PlayingState)`: Emitted when the playing state changes. Contains the new playing state.
-/// * `PositionChanged(f64)`: Emitted when the playback position changes. Contains the new position in seconds.
+/// * `RateChanged(f64)`: Emitted when the playback rate changes. Contains the new rate.
+/// * `PositionChanged(f64)`: Emitted periodically while playing, and once after each seek, when the playback position has changed. Contains the new position in seconds.
+/// * `DurationChanged(f64)`: Emitted when the duration of the media changes after it has been loaded, e.g. for a file that is still being written. Contains the new duration in seconds.
+/// * `SeekCompleted { requested, actual }`: Emitted once a seek has completed. Contains the requested position and the position the stream actually landed on, in seconds. Seeks superseded by newer ones before they are executed are not reported.
+/// * `SeekFailed { requested }`: Emitted when a seek could not be executed, or the stream did not settle at its position within 30 seconds. Contains the requested position in seconds.
+/// * `OutputSizeChanged(OutputSize)`: Emitted when the size of the frames changes. Contains the new output size.
+/// * `EndOfStream`: Emitted when the end of the media has been reached. Not emitted while the playback is looped.
+/// * `LoopIteration(u64)`: Emitted each time a loop wraps around. Contains the number of times it has wrapped since the loop was set.
+/// * `Warning { source_element, message, debug }`: Emitted when the pipeline reports a warning.
+/// * `Info { source_element, message, debug }`: Emitted when the pipeline reports an informational message.
+/// * `Closed`: Emitted when the video stream is closed.
 #[derive(Debug, Clone)]
 pub enum VideoStreamEvent {
     /// Emitted when a video is successfully loaded. Contains metadata about the video.
     VideoLoaded(VideoInfo),
     /// Emitted for each new frame. To get the actual frame data, use the `FrameHandler` trait.
     NewFrame,
-    Error(String),
-    /// Emitted when the current audio track changes. Contains the new audio track ID.
-    CurrentAudioTrackChanged(usize),
-    /// Emitted when the current subtitle track changes. Contains the new subtitle track ID.
-    CurrentSubtitleTrackChanged(usize),
+    /// Emitted when an error happens after the stream has been opened. Contains the error.
+    Error(DextreamerError),
+    /// Emitted when the current audio track changes. Contains the new audio track ID, or `None` if the audio has been disabled.
+    CurrentAudioTrackChanged(Option<usize>),
+    /// Emitted when the current subtitle track changes. Contains the new subtitle track ID, or `None` if the subtitles have been disabled.
+    CurrentSubtitleTrackChanged(Option<usize>),
     /// Emitted when the volume changes. Contains the new volume.
     VolumeChanged(f32),
     /// Emitted when the playing state changes. Contains the new playing state.
     PlayingStateChanged(PlayingState),
-    /// Emitted when the playback position changes. Contains the new position in seconds.
+    /// Emitted when the playback rate changes. Contains the new rate.
+    RateChanged(f64),
+    /// Emitted periodically while playing, and once after each seek, when the playback position
+    /// has changed. Contains the new position in seconds.
     PositionChanged(f64),
+    /// Emitted when the duration of the media changes after it has been loaded. Contains the new
+    /// duration in seconds.
+    DurationChanged(f64),
+    /// Emitted once a seek has completed. Seeks superseded by newer ones before they are executed
+    /// are not reported.
+    SeekCompleted {
+        /// The requested position, in seconds.
+        requested: f64,
+        /// The position the stream landed on, in seconds. Differs from `requested` for keyframe
+        /// seeks.
+        actual: f64,
+    },
+    /// Emitted when a seek could not be executed, or the stream did not settle at its position
+    /// within 30 seconds.
+    SeekFailed {
+        /// The requested position, in seconds.
+        requested: f64,
+    },
+    /// Emitted when the size of the frames changes. Contains the new output size.
+    OutputSizeChanged(OutputSize),
+    /// Emitted instead of `VideoLoaded` when the media could not be loaded. Contains the reason.
+    LoadFailed(DextreamerError),
+    /// Emitted when the end of the media has been reached. Not emitted while the playback is
+    /// looped.
+    EndOfStream,
+    /// Emitted each time a loop wraps around. Contains the number of times it has wrapped since
+    /// the loop was set.
+    LoopIteration(u64),
+    /// Emitted when the pipeline reports a warning. Playback continues.
+    Warning {
+        source_element: Option<String>,
+        message: String,
+        debug: Option<String>,
+    },
+    /// Emitted when the pipeline reports an informational message.
+    Info {
+        source_element: Option<String>,
+        message: String,
+        debug: Option<String>,
+    },
     /// Emitted when the video stream is closed.
     Closed,
 }
 
-/// `FrameData` contains the raw data and size of a video frame.
+/// `FrameData` is an owned copy of a video frame.
+///
+/// The planes of the frame are stored one after another in `data`, the plane with index `i`
+/// starting at `offsets[i]`. Rows of a plane can be padded for alignment, so they have to be read
+/// using `strides[i]` rather than the frame width.
 ///
 /// # Fields
 ///
-/// * `data: Vec<u8>`: The raw data of the frame. Every 4 elements represent one pixel in the RGBA format.
+/// * `data: Vec<u8>`: The raw data of all the planes of the frame.
 /// * `size: [usize; 2]`: The size of the frame in pixels. The first element is the width and the second is the height.
+/// * `format: PixelFormat`: The pixel format of the frame.
+/// * `strides: Vec<usize>`: The number of bytes between the starts of two consecutive rows, for each plane.
+/// * `offsets: Vec<usize>`: The position in `data` where each plane starts.
+/// * `pts: Option<Duration>`: The presentation timestamp of the frame, if known.
+/// * `duration: Option<Duration>`: How long the frame is displayed for, if known.
+/// * `index: u64`: The index of the frame since the stream was opened.
 #[derive(Default, Debug, Clone)]
 pub struct FrameData {
-    /// TThe raw data of the frame. Every 4 elements represent one pixel in the RGBA format.
+    /// The raw data of all the planes of the frame.
     pub data: Vec<u8>,
     /// The size of the frame in pixels. The first element is the width and the second is the height.
     pub size: [usize; 2],
+    /// The pixel format of the frame.
+    pub format: PixelFormat,
+    /// The number of bytes between the starts of two consecutive rows, for each plane.
+    pub strides: Vec<usize>,
+    /// The position in `data` where each plane starts.
+    pub offsets: Vec<usize>,
+    /// The presentation timestamp of the frame, if known.
+    pub pts: Option<Duration>,
+    /// How long the frame is displayed for, if known.
+    pub duration: Option<Duration>,
+    /// The index of the frame since the stream was opened.
+    pub index: u64,
 }
 
-fn handle_action(
-    video_action: VideoStreamAction,
-    playbin_pipeline: &gst::Element,
-    sender: &Sender<VideoStreamEvent>,
-) -> bool {
-    match video_action {
-        VideoStreamAction::SetCurrentSubtitleTrack(track_id) => {
-            // Setting a subtitle track
-            if let Some(subtitle_track_id) = track_id {
-                playbin_pipeline.set_property("current-text", subtitle_track_id as i32);
-                sender
-                    .send(VideoStreamEvent::CurrentSubtitleTrackChanged(
-                        subtitle_track_id,
-                    ))
-                    .unwrap();
-            } else {
-                todo!("disable subtitles")
-            }
-        }
-        VideoStreamAction::SetCurrentAudioTrack(audio_track_id) => {
-            // Setting an audio track
-            if let Some(audio_track_id) = audio_track_id {
-                playbin_pipeline.set_property("current-audio", audio_track_id as i32);
-                sender
-                    .send(VideoStreamEvent::CurrentAudioTrackChanged(audio_track_id))
-                    .unwrap();
-            } else {
-                todo!("disable audio")
-            }
-        }
-        VideoStreamAction::SetVolume(volume) => {
-            sender
-                .send(VideoStreamEvent::VolumeChanged(volume))
-                .unwrap();
-            playbin_pipeline.set_property("volume", volume as f64);
-        }
-        VideoStreamAction::SetPlay => {
-            playbin_pipeline.set_state(gst::State::Playing).unwrap();
-            sender
-                .send(VideoStreamEvent::PlayingStateChanged(PlayingState::Playing))
-                .unwrap();
-        }
-        VideoStreamAction::SetPause => {
-            playbin_pipeline.set_state(gst::State::Paused).unwrap();
-            sender
-                .send(VideoStreamEvent::PlayingStateChanged(PlayingState::Paused))
-                .unwrap();
-        }
-        VideoStreamAction::SeekToSeconds(seconds) => {
-            let position_ns = (seconds * 1_000_000_000.0) as u64;
-            sender
-                .send(VideoStreamEvent::PositionChanged(seconds))
-                .unwrap();
-            playbin_pipeline
-                .seek(
-                    1.0,
-                    gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
-                    gst::SeekType::Set,
-                    gst::ClockTime::from_nseconds(position_ns),
-                    gst::SeekType::None,
-                    gst::ClockTime::NONE,
-                )
-                .unwrap_or_else(|_| println!("Seek failed"));
-        }
-        VideoStreamAction::Close => {
-            playbin_pipeline.set_state(gst::State::Null).unwrap();
-            sender.send(VideoStreamEvent::Closed).unwrap();
-            return true;
-        }
+impl FrameData {
+    /// The raw data of the plane with the given index, or `None` if there is no such plane.
+    pub fn plane(&self, index: usize) -> Option<&[u8]> {
+        let start = *self.offsets.get(index)?;
+        let end = self
+            .offsets
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.data.len());
+
+        self.data.get(start..end)
     }
+}
 
-    false
+pub(crate) fn to_gst_state(playing_state: PlayingState) -> gst::State {
+    match playing_state {
+        PlayingState::Playing => gst::State::Playing,
+        PlayingState::Paused => gst::State::Paused,
+        PlayingState::Stopped => gst::State::Null,
+    }
 }
 
-fn handle_message(
-    message: InternalMessage,
+pub(crate) fn set_pipeline_state(
     playbin_pipeline: &gst::Element,
-    sender: &Sender<VideoStreamEvent>,
-) -> bool {
-    match message {
-        InternalMessage::VideoStreamAction(video_action) => {
-            handle_action(video_action, playbin_pipeline, sender)
-        }
-        InternalMessage::RequestPositionUpdate => {
-            let mut position_query = gst::query::Position::new(gst::Format::Time);
-            if playbin_pipeline.query(&mut position_query) {
-                let position_nanoseconds = match position_query.result() {
-                    gstreamer::GenericFormattedValue::Time(Some(position)) => position.nseconds(),
-                    _ => 0,
-                };
-                sender
-                    .send(VideoStreamEvent::PositionChanged(
-                        position_nanoseconds as f64 / 1_000_000_000.0,
-                    ))
-                    .unwrap();
-
-                false
-            } else {
-                println!("Position query failed");
-
-                false
-            }
-        }
-    }
+    playing_state: PlayingState,
+) -> Result<(), DextreamerError> {
+    playbin_pipeline
+        .set_state(to_gst_state(playing_state))
+        .map(|_| ())
+        .map_err(|_| DextreamerError::StateChange(playing_state))
 }
 
-fn wait_for_video_to_load(playbin_message_bus: &Bus) {
-    // Listen for messages on the pipeline's bus until `async-done` is received, which means
-    //  that media has been loaded and playback can begin.
-    while let Some(msg) = playbin_message_bus.timed_pop(gst::ClockTime::NONE) {
-        if let gst::MessageView::AsyncDone(_) = msg.view() {
-            // The async state change has completed
-            break;
-        }
-    }
+/// Checks that the uri is well-formed and that there is an installed element able to read it.
+fn validate_uri(uri: &str) -> Result<(), DextreamerError> {
+    gst::Element::make_from_uri(gst::URIType::Src, uri, None)
+        .map(|_| ())
+        .map_err(|err| DextreamerError::InvalidUri {
+            uri: uri.to_string(),
+            reason: err.to_string(),
+        })
 }
 
-/// Opens a video stream and returns a sender and receiver to communicate with the video thread.
-/// Sender is used to send actions to the video thread and receiver is used to receive events
-/// from the video thread.
+/// Opens a video stream and returns a [`VideoStream`] handle to communicate with the video thread.
+/// The handle is used to send actions to the video thread and to receive events from it. Dropping
+/// the handle closes the stream.
+///
+/// Errors that happen while setting up the pipeline, such as a missing gstreamer plugin or an
+/// invalid uri, are returned right away. Errors that happen later are delivered as
+/// `VideoStreamEvent::Error` events.
 ///
 /// # Example
-/// ```rust
+/// ```no_run
 /// struct VideoFrameLoader;
 ///
 /// impl dextreamer::FrameHandler for VideoFrameLoader {
-///    fn handle_new_frame(&self, frame_data: &[u8], frame_size: (u32, u32)) {
-///       println!("New frame: {:?}", frame_size);
+///    fn handle_new_frame(&self, frame: &dextreamer::VideoFrameRef<'_>) {
+///       println!("New frame: {}x{}", frame.width(), frame.height());
 ///   }
 /// }
 ///
-/// let (actions_sender, events_receiver) = dextreamer::open_video("file:///home/user/my_video.mkv", VideoFrameLoader);
-/// // Now you can use `actions_sender` to send actions to the video thread and `events_receiver` to receive events from the video thread.
+/// let video_stream = dextreamer::open_video("file:///home/user/my_video.mkv", VideoFrameLoader)
+///     .expect("to open the video");
+/// // Now you can use `video_stream` to send actions to the video thread and to receive events from the video thread.
 /// ```
 pub fn open_video(
     uri: impl Into<String>,
     frame_data_handler: impl FrameHandler + 'static,
-) -> (Sender<VideoStreamAction>, Receiver<VideoStreamEvent>) {
-    // Sender to send messages to the video thread
-    let (actions_sender, actions_receiver) = mpsc::channel();
-    // Receiver to receive messages from the video thread
-    let (event_sender, event_receiver) = mpsc::channel();
-
-    let uri = uri.into();
-
-    thread::spawn(move || {
-        open_video_internal(&uri, actions_receiver, event_sender, frame_data_handler);
-    });
+) -> Result<VideoStream, DextreamerError> {
+    VideoStreamBuilder::new(uri)
+        .frame_handler(frame_data_handler)
+        .open()
+}
 
-    (actions_sender, event_receiver)
+pub(crate) fn make_element(factory_name: &str) -> Result<gst::Element, DextreamerError> {
+    gst::ElementFactory::make(factory_name)
+        .build()
+        .map_err(|_| DextreamerError::MissingElement(factory_name.to_string()))
 }
 
-fn open_video_internal(
-    uri: &str,
-    receiver: Receiver<VideoStreamAction>,
-    sender: Sender<VideoStreamEvent>,
-    frame_data_handler: impl FrameHandler + 'static,
-) {
-    let (internal_sender, internal_receiver) = mpsc::channel::<InternalMessage>();
+/// Creates the element requested by the `AudioSink` setting. Returns `None` if playbin should
+/// pick the audio sink itself.
+fn make_audio_sink(audio_sink: &AudioSink) -> Result<Option<gst::Element>, DextreamerError> {
+    match audio_sink {
+        AudioSink::Auto => Ok(None),
+        AudioSink::Disabled => {
+            let fake_sink = make_element("fakesink")?;
+            // Keeps the playback running at the normal speed
+            fake_sink.set_property("sync", true);
+
+            Ok(Some(fake_sink))
+        }
+        AudioSink::Element(factory_name) => make_element(factory_name).map(Some),
+    }
+}
 
-    gst::init().expect("to initialize gstreamer without errors");
+pub(crate) fn open_video_stream(
+    uri: String,
+    config: StreamConfig,
+    frame_data_handler: Option<Box<dyn AnyFrameHandler>>,
+) -> Result<VideoStream, DextreamerError> {
+    gst::init().map_err(|err| DextreamerError::Init(err.to_string()))?;
 
-    let memory_video_sink =
-        memory_video_sink(internal_sender.clone(), sender.clone(), frame_data_handler);
+    validate_uri(&uri)?;
 
-    // Create a new playbin element, and tell it what uri to play back.
-    let playbin_pipeline = gst::ElementFactory::make("playbin")
-        .property("uri", uri)
-        .build()
-        .unwrap();
-
-    playbin_pipeline.set_property("video-sink", memory_video_sink);
+    // Sender to send messages to the video thread
+    let (internal_sender, internal_receiver) = mpsc::channel::<InternalMessage>();
+    // Receiver to receive messages from the video thread
+    let (event_sender, event_receiver) = event_channel(config.event_mask);
 
-    let playbin_message_bus = playbin_pipeline.bus().unwrap();
+    let is_paused = Arc::new(AtomicBool::new(!config.autoplay));
 
-    playbin_pipeline
-        .set_state(gst::State::Playing)
-        .expect("Unable to set the pipeline to the `Playing` state");
-
-    wait_for_video_to_load(&playbin_message_bus);
-
-    let bus_thread_handle = thread::spawn(move || {
-        for msg in playbin_message_bus.iter_timed(gst::ClockTime::NONE) {
-            use gst::MessageView;
-
-            match msg.view() {
-                MessageView::Eos(..) => break,
-                MessageView::Error(err) => {
-                    println!(
-                        "Error from {:?}: {} ({:?})",
-                        err.src().map(|s| s.path_string()),
-                        err.error(),
-                        err.debug()
-                    );
-                    break;
-                }
-                MessageView::StateChanged(_state_changed) =>
-                // We are only interested in state-changed messages from playbin
-                {
-                    // println!("state changed");
-                    // if state_changed
-                    //     .src()
-                    //     .map(|s| s == &playbin_pipeline)
-                    //     .unwrap_or(false)
-                    //     && state_changed.current() == gst::State::Playing
-                    // {
-                    //     // Generate a dot graph of the pipeline to GST_DEBUG_DUMP_DOT_DIR if defined
-                    //     let bin_ref = playbin_pipeline.downcast_ref::<gst::Bin>().unwrap();
-                    //     bin_ref.debug_to_dot_file(gst::DebugGraphDetails::all(), "PLAYING");
-                    // }
-                }
-
-                _ => (),
-            }
+    let (mailbox_writer, mailbox_reader) = if config.frame_mailbox {
+        let (writer, reader) = frame_mailbox();
+        (Some(writer), Some(reader))
+    } else {
+        (None, None)
+    };
+    let (frame_sender, frame_receiver) = match config.frame_queue {
+        Some((depth, drop_policy)) => {
+            let (sender, receiver) = frame_queue(depth, drop_policy);
+            (Some(sender), Some(receiver))
         }
-    });
+        None => (None, None),
+    };
 
-    let action_receiver_thread_handle = thread::spawn(move || {
-        while let Some(message) = receiver.iter().next() {
-            let needs_to_be_closed = matches!(&message, VideoStreamAction::Close);
+    let memory_video_sink = memory_video_sink(
+        internal_sender.clone(),
+        event_sender.clone(),
+        FrameOutputs {
+            frame_data_handler,
+            mailbox: mailbox_writer,
+            frame_sender,
+            handler_worker: None,
+        },
+        is_paused.clone(),
+        &config,
+    )?;
 
-            internal_sender
-                .send(InternalMessage::VideoStreamAction(message))
-                .unwrap();
+    // Create a new playbin element, and tell it what uri to play back.
+    let playbin_pipeline = make_element("playbin")?;
+    playbin_pipeline.set_property("uri", &uri);
+    playbin_pipeline.set_property("video-sink", memory_video_sink.bin());
+    playbin_pipeline.set_property("volume", config.volume as f64);
 
-            if needs_to_be_closed {
-                break;
-            }
+    if let Some(audio_sink) = make_audio_sink(&config.audio_sink)? {
+        playbin_pipeline.set_property("audio-sink", audio_sink);
+    }
+    if config.audio_sink != AudioSink::Disabled {
+        // Keeps the pitch of the audio when the playback rate is changed. Without it, the stream
+        //  still plays, but only at the normal rate.
+        if let Ok(scaletempo) = make_element("scaletempo") {
+            playbin_pipeline.set_property("audio-filter", scaletempo);
         }
-    });
-
-    let video_state = VideoInfo {
-        title: "Test title.mkv".to_string(),
-        current_subtitle_track: Some(0),
-        current_audio_track: Some(0),
-        volume: 1.0,
-        subtitle_tracks: subtitle_tracks(&playbin_pipeline),
-        audio_tracks: audio_tracks(&playbin_pipeline),
-        playing_state: PlayingState::Playing,
-        duration: video_duration(&playbin_pipeline),
-        current_position: 0.0,
-    };
+    }
+    // The tracks themselves can only be selected once the media is loaded, but disabling them
+    //  right away avoids a short burst of audio or subtitles.
+    if let Some(audio_track) = config.audio_track {
+        set_playbin_flag(&playbin_pipeline, PLAYBIN_FLAG_AUDIO, audio_track.is_some());
+    }
+    if let Some(subtitle_track) = config.subtitle_track {
+        set_playbin_flag(
+            &playbin_pipeline,
+            PLAYBIN_FLAG_TEXT,
+            subtitle_track.is_some(),
+        );
+    }
 
-    // TODO: handle error
-    sender
-        .send(VideoStreamEvent::VideoLoaded(video_state))
-        .expect("to notify the video has loaded");
+    let sink_handle = memory_video_sink.handle();
 
-    while let Some(message) = internal_receiver.iter().next() {
-        let needs_to_close_stream = handle_message(message, &playbin_pipeline, &sender);
+    let playbin_message_bus = playbin_pipeline
+        .bus()
+        .expect("a pipeline to always have a bus");
 
-        if needs_to_close_stream {
-            break;
-        }
+    let initial_state = if config.autoplay {
+        PlayingState::Playing
+    } else {
+        PlayingState::Paused
+    };
+
+    if let Err(err) = set_pipeline_state(&playbin_pipeline, initial_state) {
+        // The element that failed, e.g. a source that can't find its file, posts the reason
+        let err = pop_pipeline_error(&playbin_message_bus).unwrap_or(err);
+        let _ = playbin_pipeline.set_state(gst::State::Null);
+        return Err(err);
     }
 
-    playbin_pipeline
-        .set_state(gst::State::Null)
-        .expect("Unable to set the pipeline to the `Null` state");
+    let bus_thread_handle = {
+        let playbin_message_bus = playbin_message_bus.clone();
+        let internal_sender = internal_sender.clone();
+        let event_sender = event_sender.clone();
+        let load_timeout = config.load_timeout;
+
+        thread::spawn(move || {
+            watch_bus(
+                &playbin_message_bus,
+                &internal_sender,
+                &event_sender,
+                load_timeout,
+            )
+        })
+    };
 
-    action_receiver_thread_handle.join().unwrap();
-    bus_thread_handle.join().unwrap();
+    let control_thread_handle = {
+        let controller = StreamController::new(
+            playbin_pipeline.clone(),
+            memory_video_sink,
+            event_sender,
+            config,
+            is_paused,
+        );
+
+        thread::spawn(move || {
+            controller.run(&playbin_message_bus, bus_thread_handle, internal_receiver)
+        })
+    };
 
-    println!("All video rendering threads closed");
+    Ok(VideoStream::new(
+        playbin_pipeline,
+        internal_sender,
+        event_receiver,
+        mailbox_reader,
+        frame_receiver,
+        sink_handle,
+        control_thread_handle,
+    ))
 }
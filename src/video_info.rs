@@ -31,30 +31,26 @@ pub struct AudioTrack {
 /// * `Playing`: The video is currently playing.
 /// * `Paused`: The video is currently paused.
 /// * `Stopped`: The video is currently stopped. Currently unused.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum PlayingState {
     Playing,
+    #[default]
     Paused,
     Stopped,
 }
 
-impl Default for PlayingState {
-    fn default() -> Self {
-        Self::Paused
-    }
-}
-
 /// `VideoInfo` contains detailed information about a video.
 ///
 /// # Fields
 ///
 /// * `title: String`: The title of the video.
-/// * `current_subtitle_track: Option<usize>`: The currently selected subtitle track, represented by its index in the `subtitle_tracks` vector. If `None`, no subtitle track is currently selected.
-/// * `current_audio_track: usize`: The currently selected audio track, represented by its index in the `audio_tracks` vector.
+/// * `current_subtitle_track: Option<usize>`: The currently selected subtitle track, represented by its index in the `subtitle_tracks` vector. If `None`, subtitles are disabled.
+/// * `current_audio_track: Option<usize>`: The currently selected audio track, represented by its index in the `audio_tracks` vector. If `None`, audio is disabled.
 /// * `volume: f32`: The current volume level of the video playback. The volume level is a value between 0.0 and 1.0, with 0.0 being silent and 1.0 being the maximum volume.
 /// * `subtitle_tracks: Vec<SubtitleTrack>`: A vector of the available subtitle tracks.
 /// * `audio_tracks: Vec<AudioTrack>`: A vector of the available audio tracks.
 /// * `playing_state: PlayingState`: The current playback state of the video (e.g., playing, paused).
+/// * `rate: f64`: The current playback rate, 1.0 being the normal speed. Negative rates play the video backwards.
 /// * `duration: f64`: The total duration of the video in seconds.
 /// * `current_position: f64`: The current playback position in the video in seconds. This value should be between 0 and `duration`.
 ///
@@ -71,6 +67,7 @@ impl Default for PlayingState {
 ///     subtitle_tracks: vec![],
 ///     audio_tracks: vec![],
 ///     playing_state: PlayingState::Paused,
+///     rate: 1.0,
 ///     duration: 600.0,
 ///     current_position: 0.0,
 /// };
@@ -84,6 +81,7 @@ pub struct VideoInfo {
     pub subtitle_tracks: Vec<SubtitleTrack>,
     pub audio_tracks: Vec<AudioTrack>,
     pub playing_state: PlayingState,
+    pub rate: f64,
     pub duration: f64,
     pub current_position: f64,
 }
@@ -98,6 +96,7 @@ impl VideoInfo {
             volume: 1.0,
             current_audio_track: Some(0),
             playing_state: PlayingState::Stopped,
+            rate: 1.0,
             duration: 0.0,
             current_position: 0.0,
         }
@@ -1,47 +1,184 @@
-use crate::frame_handler::FrameHandler;
+use crate::builder::StreamConfig;
+use crate::events::EventSender;
+use crate::frame_handler::{apply_frame_control, AnyFrameHandler, HandlerId, HandlerOptions};
+use crate::frame_queue::{FrameQueueCloser, FrameSender};
+use crate::handler_worker::{HandlerWorker, WorkerSender};
+use crate::mailbox::MailboxWriter;
+use crate::statistics::{FrameCounters, SinkStatistics};
+use crate::streamer::make_element;
 use crate::streamer::InternalMessage;
-use crate::VideoStreamEvent;
+use crate::{
+    DextreamerError, DropPolicy, OutputSize, PixelFormat, VideoFrameRef, VideoStreamEvent,
+};
 use gst::element_error;
+use gst::prelude::*;
 use gstreamer as gst;
 use gstreamer_app as gst_app;
 use gstreamer_app::AppSink;
 use gstreamer_video as gst_video;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
-pub(crate) fn memory_video_sink(
+/// How late a frame can be before it is dropped when QoS is enabled, the value used by the video
+/// sinks of gstreamer.
+const QOS_MAX_LATENESS: gst::ClockTime = gst::ClockTime::from_mseconds(20);
+
+/// The places the decoded frames are delivered to, as configured with the `VideoStreamBuilder`.
+pub(crate) struct FrameOutputs {
+    pub(crate) frame_data_handler: Option<Box<dyn AnyFrameHandler>>,
+    pub(crate) mailbox: Option<MailboxWriter>,
+    pub(crate) frame_sender: Option<FrameSender>,
+    /// Set instead of `frame_data_handler` when the handler runs on a thread of its own.
+    pub(crate) handler_worker: Option<WorkerSender>,
+}
+
+/// `FrameStepping` is shared by the primary frame sink and the control thread, to report the
+/// frame reached by `VideoStreamAction::StepFrames`.
+pub(crate) struct FrameStepping {
+    /// The stream time of the last frame delivered by the primary sink, in nanoseconds, or
+    /// `u64::MAX` if it is unknown.
+    last_frame_position: AtomicU64,
+    /// The number of frames left to deliver until the frame the stream is stepping to.
+    pending_frames: AtomicU32,
+}
+
+impl FrameStepping {
+    /// Returns the stream time of the last delivered frame, in seconds.
+    pub(crate) fn last_frame_position(&self) -> Option<f64> {
+        match self.last_frame_position.load(Ordering::SeqCst) {
+            u64::MAX => None,
+            position => Some(position as f64 / 1_000_000_000.0),
+        }
+    }
+
+    /// Forgets the last delivered frame, whose position no longer applies after a flushing seek.
+    /// Returns the forgotten position, to be restored if the seek fails.
+    pub(crate) fn forget_last_frame(&self) -> u64 {
+        self.last_frame_position.swap(u64::MAX, Ordering::SeqCst)
+    }
+
+    /// Restores a position returned by `forget_last_frame`, unless a frame has been delivered
+    /// since then.
+    pub(crate) fn restore_last_frame(&self, position: u64) {
+        let _ = self.last_frame_position.compare_exchange(
+            u64::MAX,
+            position,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+
+    /// Reports the position of the `frames`th frame delivered from now on with
+    /// `InternalMessage::FrameStepped`.
+    pub(crate) fn expect_frames(&self, frames: u32) {
+        self.pending_frames.store(frames, Ordering::SeqCst);
+    }
+
+    /// Records the position of a delivered frame. Returns `true` if it is the frame the stream
+    /// is stepping to.
+    fn frame_delivered(&self, position: Option<gst::ClockTime>) -> bool {
+        let position = position.map_or(u64::MAX, gst::ClockTime::nseconds);
+        self.last_frame_position.store(position, Ordering::SeqCst);
+
+        self.pending_frames
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |frames| {
+                frames.checked_sub(1)
+            })
+            == Ok(1)
+    }
+}
+
+/// `FrameSink` hands the samples received by the appsink to the frame handler and notifies the
+/// other threads about them.
+struct FrameSink {
     internal_sender: Sender<InternalMessage>,
-    external_sender: Sender<VideoStreamEvent>,
-    frame_data_handler: impl FrameHandler + 'static,
-) -> AppSink {
-    let video_format = gst_video::VideoCapsBuilder::new()
-        .format(gst_video::VideoFormat::Rgba)
-        .build();
+    external_sender: EventSender,
+    outputs: FrameOutputs,
+    counters: Arc<FrameCounters>,
+    /// Only the sink of the handler set with the `VideoStreamBuilder` emits
+    /// `VideoStreamEvent::NewFrame`, the sinks of the added handlers don't.
+    is_primary: bool,
+    /// Only set for the primary sink.
+    stepping: Option<Arc<FrameStepping>>,
+    /// The last buffer delivered while paused. The sink renders it again once the playback
+    /// resumes, and it is skipped then so that it is only delivered once. Holding a reference
+    /// keeps its address from being reused by another buffer.
+    prerolled_buffer: Option<gst::Buffer>,
+    /// The parsed caps of the last sample, reused as long as the caps don't change.
+    video_info: Option<(gst::Caps, gst_video::VideoInfo, PixelFormat)>,
+    frame_index: u64,
+}
 
-    let appsink = gst_app::AppSink::builder().caps(&video_format).build();
+impl FrameSink {
+    /// Returns `true` if the sample holds the buffer that has already been delivered as the
+    /// preroll.
+    fn is_prerolled(&mut self, sample: &gst::Sample) -> bool {
+        let Some(prerolled_buffer) = self.prerolled_buffer.take() else {
+            return false;
+        };
 
-    let sink_callback = gst_app::AppSinkCallbacks::builder()
-        // Add a handler to the "new-sample" signal.
-        .new_sample(move |appsink| {
-            // Pull the sample in question out of the appsink's buffer.
-            let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
-            let buffer = sample.buffer().ok_or_else(|| {
-                element_error!(
-                    appsink,
-                    gst::ResourceError::Failed,
-                    ("Failed to get buffer from appsink")
-                );
+        sample
+            .buffer()
+            .is_some_and(|buffer| buffer.as_ptr() == prerolled_buffer.as_ptr())
+    }
 
-                gst::FlowError::Error
-            })?;
+    /// Returns the video info of the sample, parsing its caps only if they have changed since the
+    /// previous sample.
+    fn video_info(&mut self, sample: &gst::Sample) -> Option<(&gst_video::VideoInfo, PixelFormat)> {
+        let caps = sample.caps_owned()?;
+
+        let is_cached = matches!(
+            &self.video_info,
+            Some((cached_caps, _, _)) if cached_caps.as_ptr() == caps.as_ptr()
+        );
+
+        if !is_cached {
+            let info = gst_video::VideoInfo::from_caps(&caps).ok()?;
+            let format = PixelFormat::from_video_format(info.format())?;
+            self.video_info = Some((caps, info, format));
+        }
 
-            // At this point, buffer is only a reference to an existing memory region somewhere.
-            // When we want to access its content, we have to map it while requesting the required
-            // mode of access (read, read/write).
-            // This type of abstraction is necessary, because the buffer in question might not be
-            // on the machine's main memory itself, but rather in the GPU's memory.
-            // So mapping the buffer makes the underlying memory region accessible to us.
-            // See: https://gstreamer.freedesktop.org/documentation/plugin-development/advanced/allocation.html
-            let map = buffer.map_readable().map_err(|_| {
+        self.video_info
+            .as_ref()
+            .map(|(_, info, format)| (info, *format))
+    }
+
+    fn handle_sample(
+        &mut self,
+        appsink: &AppSink,
+        sample: &gst::Sample,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let buffer = sample.buffer().ok_or_else(|| {
+            element_error!(
+                appsink,
+                gst::ResourceError::Failed,
+                ("Failed to get buffer from appsink")
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let (info, format) = self.video_info(sample).ok_or_else(|| {
+            element_error!(
+                appsink,
+                gst::CoreError::Negotiation,
+                ("Failed to parse the sample caps")
+            );
+
+            gst::FlowError::NotNegotiated
+        })?;
+
+        // At this point, buffer is only a reference to an existing memory region somewhere.
+        // When we want to access its content, we have to map it while requesting the required
+        // mode of access (read, read/write).
+        // This type of abstraction is necessary, because the buffer in question might not be
+        // on the machine's main memory itself, but rather in the GPU's memory.
+        // So mapping the buffer makes the underlying memory region accessible to us.
+        // See: https://gstreamer.freedesktop.org/documentation/plugin-development/advanced/allocation.html
+        let frame =
+            gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, info).map_err(|_| {
                 element_error!(
                     appsink,
                     gst::ResourceError::Failed,
@@ -50,23 +187,501 @@ pub(crate) fn memory_video_sink(
 
                 gst::FlowError::Error
             })?;
+        let frame = VideoFrameRef::new(frame, format, self.frame_index);
+        self.frame_index += 1;
+        self.counters.delivered.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(frame_data_handler) = &mut self.outputs.frame_data_handler {
+            let result = frame_data_handler.handle_new_frame(&frame);
+            apply_frame_control(result, &self.internal_sender, &self.external_sender)
+                .map_err(|_| gst::FlowError::Flushing)?;
+        }
+
+        if let Some(handler_worker) = &self.outputs.handler_worker {
+            match frame.retain() {
+                Some(frame) => handler_worker.submit(frame),
+                None => {
+                    self.counters
+                        .handler_dropped
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
 
-            let caps = sample.caps().expect("Expect caps to exist");
-            let info = gst_video::VideoInfo::from_caps(caps).expect("Failed to parse caps");
+        if let Some(mailbox) = &mut self.outputs.mailbox {
+            mailbox.publish(|frame_data| frame.copy_to(frame_data));
+        }
 
-            internal_sender
-                .send(InternalMessage::RequestPositionUpdate)
-                .unwrap();
+        // The queue is only closed when the receiver is dropped or the stream shuts down, in
+        //  which case the frames are not wanted anymore
+        if let Some(frame_sender) = &self.outputs.frame_sender {
+            frame_sender.send_with(|frame_data| frame.copy_to(frame_data));
+        }
 
-            frame_data_handler.handle_new_frame(map.as_slice(), (info.width(), info.height()));
+        if let Some(stepping) = &self.stepping {
+            let position = buffer.pts().and_then(|pts| {
+                sample
+                    .segment()?
+                    .downcast_ref::<gst::ClockTime>()?
+                    .to_stream_time(pts)
+            });
+            if stepping.frame_delivered(position) {
+                let position =
+                    position.map(|position| position.nseconds() as f64 / 1_000_000_000.0);
+                self.internal_sender
+                    .send(InternalMessage::FrameStepped(position))
+                    .map_err(|_| gst::FlowError::Flushing)?;
+            }
+        }
 
-            external_sender.send(VideoStreamEvent::NewFrame).unwrap();
+        if self.is_primary {
+            self.external_sender
+                .send(VideoStreamEvent::NewFrame)
+                .map_err(|_| gst::FlowError::Flushing)?;
+        }
 
-            Ok(gst::FlowSuccess::Ok)
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+/// The elements scaling the frames of one branch of the sink bin to the requested output size.
+struct ScaleElements {
+    /// Only available if the `videocrop` plugin from gst-plugins-good is installed.
+    aspect_ratio_crop: Option<gst::Element>,
+    video_scale: gst::Element,
+    caps_filter: gst::Element,
+    pixel_formats: Vec<PixelFormat>,
+}
+
+impl ScaleElements {
+    fn new(pixel_formats: Vec<PixelFormat>) -> Result<Self, DextreamerError> {
+        Ok(Self {
+            aspect_ratio_crop: make_element("aspectratiocrop").ok(),
+            video_scale: make_element("videoscale")?,
+            caps_filter: make_element("capsfilter")?,
+            pixel_formats,
+        })
+    }
+
+    /// The elements in the order they are linked.
+    fn elements(&self) -> impl Iterator<Item = &gst::Element> {
+        self.aspect_ratio_crop
+            .iter()
+            .chain([&self.video_scale, &self.caps_filter])
+    }
+
+    /// Changes the size of the frames. Takes effect with the next frame.
+    fn set_output_size(&self, output_size: OutputSize) -> Result<(), DextreamerError> {
+        let crop_aspect_ratio = output_size.crop_aspect_ratio();
+
+        match (&self.aspect_ratio_crop, crop_aspect_ratio) {
+            (Some(aspect_ratio_crop), aspect_ratio) => {
+                // A 0/1 aspect ratio disables the cropping
+                let aspect_ratio = aspect_ratio.unwrap_or_else(|| gst::Fraction::new(0, 1));
+                aspect_ratio_crop.set_property("aspect-ratio", aspect_ratio);
+            }
+            (None, Some(_)) => {
+                return Err(DextreamerError::MissingElement(
+                    "aspectratiocrop".to_string(),
+                ))
+            }
+            (None, None) => (),
+        }
+
+        self.video_scale
+            .set_property("add-borders", output_size.adds_borders());
+        self.caps_filter
+            .set_property("caps", output_size.to_caps(&self.pixel_formats));
+
+        Ok(())
+    }
+}
+
+/// The branch of an added frame handler.
+struct FrameBranch {
+    tee_pad: gst::Pad,
+    elements: Vec<gst::Element>,
+}
+
+/// `FrameBranches` adds and removes the branches of the frame handlers added to a live stream.
+/// Every branch has its own queue, so a slow handler only drops its own frames.
+pub(crate) struct FrameBranches {
+    bin: gst::Bin,
+    tee: gst::Element,
+    internal_sender: Sender<InternalMessage>,
+    external_sender: EventSender,
+    is_paused: Arc<AtomicBool>,
+    sync: bool,
+    branches: Mutex<HashMap<HandlerId, FrameBranch>>,
+    next_id: AtomicU64,
+}
+
+impl FrameBranches {
+    pub(crate) fn add(
+        &self,
+        frame_handler: Box<dyn AnyFrameHandler>,
+        options: HandlerOptions,
+    ) -> Result<HandlerId, DextreamerError> {
+        let queue = make_element("queue")?;
+        queue.set_property_from_str("leaky", "downstream");
+        let video_convert = make_element("videoconvert")?;
+        let scale_elements = ScaleElements::new(options.pixel_formats)?;
+        scale_elements.set_output_size(options.output_size)?;
+
+        let appsink = gst_app::AppSink::builder().sync(self.sync).build();
+        // The pipeline can't wait for the branch to preroll once it is already running
+        appsink.set_property("async", false);
+
+        let frame_sink = FrameSink {
+            internal_sender: self.internal_sender.clone(),
+            external_sender: self.external_sender.clone(),
+            outputs: FrameOutputs {
+                frame_data_handler: Some(frame_handler),
+                mailbox: None,
+                frame_sender: None,
+                handler_worker: None,
+            },
+            counters: Arc::default(),
+            is_primary: false,
+            stepping: None,
+            prerolled_buffer: None,
+            video_info: None,
+            frame_index: 0,
+        };
+        set_frame_sink_callbacks(&appsink, frame_sink, self.is_paused.clone());
+
+        let elements: Vec<gst::Element> = [&queue, &video_convert]
+            .into_iter()
+            .chain(scale_elements.elements())
+            .chain([appsink.upcast_ref()])
+            .cloned()
+            .collect();
+
+        let element_refs: Vec<&gst::Element> = elements.iter().collect();
+        let tee_pad = match self.link_branch(&element_refs) {
+            Ok(tee_pad) => tee_pad,
+            Err(err) => {
+                // The elements haven't been started yet, they can be removed right away
+                for element in &elements {
+                    if element.parent().as_ref() == Some(self.bin.upcast_ref()) {
+                        let _ = self.bin.remove(element);
+                    }
+                }
+                return Err(err);
+            }
+        };
+
+        for element in &elements {
+            let _ = element.sync_state_with_parent();
+        }
+
+        let handler_id = HandlerId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.lock_branches()
+            .insert(handler_id, FrameBranch { tee_pad, elements });
+
+        Ok(handler_id)
+    }
+
+    /// Adds the elements of a branch to the bin and links them, and then the branch to the tee.
+    /// Returns the tee pad feeding the branch.
+    fn link_branch(&self, elements: &[&gst::Element]) -> Result<gst::Pad, DextreamerError> {
+        self.bin.add_many(elements).map_err(|err| {
+            DextreamerError::FrameBranch(format!("the elements can't be added to the bin: {err}"))
+        })?;
+        gst::Element::link_many(elements).map_err(|err| {
+            DextreamerError::FrameBranch(format!("the elements can't be linked: {err}"))
+        })?;
+
+        let tee_pad = self.tee.request_pad_simple("src_%u").ok_or_else(|| {
+            DextreamerError::FrameBranch("the tee has no source pad left".to_string())
+        })?;
+        let linked = elements
+            .first()
+            .and_then(|queue| queue.static_pad("sink"))
+            .is_some_and(|queue_pad| tee_pad.link(&queue_pad).is_ok());
+        if !linked {
+            self.tee.release_request_pad(&tee_pad);
+            return Err(DextreamerError::FrameBranch(
+                "the branch can't be linked to the tee".to_string(),
+            ));
+        }
+
+        Ok(tee_pad)
+    }
+
+    pub(crate) fn remove(&self, handler_id: HandlerId) -> Result<(), DextreamerError> {
+        let FrameBranch { tee_pad, elements } = self
+            .lock_branches()
+            .remove(&handler_id)
+            .ok_or(DextreamerError::UnknownFrameHandler(handler_id))?;
+
+        let bin = self.bin.clone();
+        let tee = self.tee.clone();
+
+        // The branch can only be unlinked while no buffer is going through the tee pad. The probe
+        //  is called right away if that's already the case.
+        tee_pad.add_probe(gst::PadProbeType::IDLE, move |tee_pad, _| {
+            if let Some(queue_pad) = tee_pad.peer() {
+                let _ = tee_pad.unlink(&queue_pad);
+            }
+            tee.release_request_pad(tee_pad);
+
+            for element in &elements {
+                let _ = element.set_state(gst::State::Null);
+            }
+            let element_refs: Vec<&gst::Element> = elements.iter().collect();
+            let _ = bin.remove_many(&element_refs);
+
+            gst::PadProbeReturn::Remove
+        });
+
+        Ok(())
+    }
+
+    fn lock_branches(&self) -> MutexGuard<'_, HashMap<HandlerId, FrameBranch>> {
+        self.branches.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The parts of the video sink that are accessed from the `VideoStream` rather than from the
+/// control thread.
+pub(crate) struct SinkHandle {
+    pub(crate) statistics: SinkStatistics,
+    pub(crate) branches: Arc<FrameBranches>,
+}
+
+/// `VideoSinkBin` is the bin set as the playbin video sink. It scales the frames to the
+/// requested output size before handing them to the appsink, so the scaling happens in the
+/// gstreamer pipeline rather than in the frame handler.
+///
+/// A tee at the start of the bin feeds the branches of the frame handlers added to the live
+/// stream, see `FrameBranches`.
+pub(crate) struct VideoSinkBin {
+    bin: gst::Bin,
+    scale_elements: ScaleElements,
+    frame_queue_closer: Option<FrameQueueCloser>,
+    handler_worker: Option<HandlerWorker>,
+    statistics: SinkStatistics,
+    branches: Arc<FrameBranches>,
+    appsink: AppSink,
+    stepping: Arc<FrameStepping>,
+}
+
+impl VideoSinkBin {
+    pub(crate) fn bin(&self) -> &gst::Bin {
+        &self.bin
+    }
+
+    /// Returns the parts of the sink used by the `VideoStream`.
+    pub(crate) fn handle(&self) -> SinkHandle {
+        SinkHandle {
+            statistics: self.statistics.clone(),
+            branches: self.branches.clone(),
+        }
+    }
+
+    /// Stops delivering frames to the frame queue, so that the streaming thread can't stay blocked
+    /// on it while the pipeline is stopped.
+    pub(crate) fn close_frame_queue(&self) {
+        if let Some(frame_queue_closer) = &self.frame_queue_closer {
+            frame_queue_closer.close();
+        }
+    }
+
+    /// Stops the thread running the frame handler, if any. Only called once the pipeline is
+    /// stopped, so that no frames are handed to it anymore.
+    pub(crate) fn stop_handler_worker(&mut self) {
+        if let Some(handler_worker) = &mut self.handler_worker {
+            handler_worker.stop();
+        }
+    }
+
+    pub(crate) fn stepping(&self) -> &FrameStepping {
+        &self.stepping
+    }
+
+    /// Skips `frames` frames in the direction of the last seek. Only the appsink of the frame
+    /// handler set with the `VideoStreamBuilder` is stepped, the audio is left untouched.
+    pub(crate) fn step(&self, frames: u32) -> bool {
+        let step = gst::event::Step::new(
+            gst::format::Buffers::from_u64(frames.into()),
+            1.0,
+            true,
+            false,
+        );
+        self.appsink.send_event(step)
+    }
+
+    /// Changes the size of the frames. Takes effect with the next frame.
+    pub(crate) fn set_output_size(&self, output_size: OutputSize) -> Result<(), DextreamerError> {
+        self.scale_elements.set_output_size(output_size)
+    }
+}
+
+/// Hands the samples of the appsink to the frame sink.
+fn set_frame_sink_callbacks(appsink: &AppSink, frame_sink: FrameSink, is_paused: Arc<AtomicBool>) {
+    // Both callbacks are called from the streaming thread, the mutex is never contended
+    let frame_sink = Arc::new(Mutex::new(frame_sink));
+    let preroll_frame_sink = frame_sink.clone();
+
+    let sink_callback = gst_app::AppSinkCallbacks::builder()
+        // Add a handler to the "new-sample" signal.
+        .new_sample(move |appsink| {
+            // Pull the sample in question out of the appsink's buffer.
+            let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+
+            let mut frame_sink = frame_sink.lock().map_err(|_| gst::FlowError::Error)?;
+            if frame_sink.is_prerolled(&sample) {
+                return Ok(gst::FlowSuccess::Ok);
+            }
+            frame_sink.handle_sample(appsink, &sample)
+        })
+        // Add a handler to the "new-preroll" signal, which is emitted with the first frame after
+        //  the pipeline goes to the `Paused` state or seeks while paused.
+        .new_preroll(move |appsink| {
+            let sample = appsink.pull_preroll().map_err(|_| gst::FlowError::Eos)?;
+
+            // While playing, the same frame is delivered through "new-sample" right after
+            if !is_paused.load(Ordering::SeqCst) {
+                return Ok(gst::FlowSuccess::Ok);
+            }
+
+            let mut frame_sink = preroll_frame_sink
+                .lock()
+                .map_err(|_| gst::FlowError::Error)?;
+            frame_sink.prerolled_buffer = sample.buffer_owned();
+            frame_sink.handle_sample(appsink, &sample)
         })
         .build();
 
     appsink.set_callbacks(sink_callback);
+}
+
+pub(crate) fn memory_video_sink(
+    internal_sender: Sender<InternalMessage>,
+    external_sender: EventSender,
+    mut outputs: FrameOutputs,
+    is_paused: Arc<AtomicBool>,
+    config: &StreamConfig,
+) -> Result<VideoSinkBin, DextreamerError> {
+    let drops_oldest = config.sink_drop_policy == DropPolicy::DropOldest;
+    let appsink = gst_app::AppSink::builder()
+        .max_buffers(config.max_queued_buffers.unwrap_or(0))
+        .drop(drops_oldest)
+        .sync(config.sync)
+        .build();
+    appsink.set_qos(config.qos);
+    if config.qos {
+        // Drops the frames that are too late to be displayed, like the video sinks do
+        appsink.set_max_lateness(QOS_MAX_LATENESS.nseconds() as i64);
+    }
+    let counters = Arc::new(FrameCounters::default());
+    let stepping = Arc::new(FrameStepping {
+        last_frame_position: AtomicU64::new(u64::MAX),
+        pending_frames: AtomicU32::new(0),
+    });
+    let frame_queue_closer = outputs.frame_sender.as_ref().map(FrameSender::closer);
+
+    let handler_worker = match (
+        config.handler_queue_depth,
+        outputs.frame_data_handler.take(),
+    ) {
+        (Some(depth), Some(frame_data_handler)) => {
+            let (handler_worker, worker_sender) = HandlerWorker::spawn(
+                frame_data_handler,
+                depth,
+                counters.clone(),
+                internal_sender.clone(),
+                external_sender.clone(),
+            );
+            outputs.handler_worker = Some(worker_sender);
+
+            Some(handler_worker)
+        }
+        (_, frame_data_handler) => {
+            outputs.frame_data_handler = frame_data_handler;
+            None
+        }
+    };
+
+    let frame_sink = FrameSink {
+        internal_sender: internal_sender.clone(),
+        external_sender: external_sender.clone(),
+        outputs,
+        counters: counters.clone(),
+        is_primary: true,
+        stepping: Some(stepping.clone()),
+        prerolled_buffer: None,
+        video_info: None,
+        frame_index: 0,
+    };
+    set_frame_sink_callbacks(&appsink, frame_sink, is_paused.clone());
+
+    let tee = make_element("tee")?;
+    // Branches can be removed at any time, without stopping the others
+    tee.set_property("allow-not-linked", true);
+    let scale_elements = ScaleElements::new(config.pixel_formats.clone())?;
+    // The callbacks run on the streaming thread, so the appsink itself never holds more than one
+    //  buffer. The queue moves them to a thread of their own, so that a slow frame handler doesn't
+    //  stall the decoding and the audio.
+    let queue = config
+        .max_queued_buffers
+        .map(|max_queued_buffers| {
+            let queue = make_element("queue")?;
+            queue.set_property("max-size-buffers", max_queued_buffers);
+            queue.set_property("max-size-bytes", 0u32);
+            queue.set_property("max-size-time", 0u64);
+            if drops_oldest {
+                queue.set_property_from_str("leaky", "downstream");
+            }
+
+            Ok::<_, DextreamerError>(queue)
+        })
+        .transpose()?;
+
+    let bin = gst::Bin::new(Some("dextreamer-video-sink"));
+    let elements: Vec<&gst::Element> = [&tee]
+        .into_iter()
+        .chain(scale_elements.elements())
+        .chain(queue.iter())
+        .chain([appsink.upcast_ref()])
+        .collect();
+
+    bin.add_many(&elements)
+        .expect("the sink elements to be added to a new bin");
+    gst::Element::link_many(&elements).expect("the sink elements to accept any video caps");
+
+    let sink_pad = tee.static_pad("sink").expect("the tee to have a sink pad");
+    let ghost_pad = gst::GhostPad::with_target(Some("sink"), &sink_pad)
+        .expect("the ghost pad to accept the sink pad as target");
+    bin.add_pad(&ghost_pad)
+        .expect("the bin to accept the ghost pad");
+
+    scale_elements.set_output_size(config.output_size)?;
+
+    let branches = Arc::new(FrameBranches {
+        bin: bin.clone(),
+        tee,
+        internal_sender,
+        external_sender,
+        is_paused,
+        sync: config.sync,
+        branches: Mutex::default(),
+        next_id: AtomicU64::new(0),
+    });
 
-    appsink
+    Ok(VideoSinkBin {
+        bin,
+        scale_elements,
+        frame_queue_closer,
+        handler_worker,
+        statistics: SinkStatistics::new(
+            appsink.clone(),
+            queue.as_ref().filter(|_| drops_oldest),
+            counters,
+        ),
+        branches,
+        appsink,
+        stepping,
+    })
 }
@@ -0,0 +1,270 @@
+use gst::prelude::*;
+use gstreamer as gst;
+
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::completion::completion;
+use crate::events::EventReceiver;
+use crate::frame_handler::{Fallible, Infallible};
+use crate::frame_queue::FrameReceiver;
+use crate::mailbox::FrameMailbox;
+use crate::streamer::InternalMessage;
+use crate::video_sink::SinkHandle;
+#[cfg(feature = "async")]
+use crate::{Completion, EventStream, PlayingState};
+use crate::{
+    DextreamerError, FrameHandler, FrameHandlerMut, HandlerId, HandlerOptions, LoopMode,
+    OutputSize, SeekMode, SeekTarget, StreamStatistics, VideoStreamAction, VideoStreamEvent,
+};
+
+/// `VideoStream` is a handle to an opened video stream.
+///
+/// It owns the gstreamer pipeline and the threads driving it. Actions are sent to the stream
+/// with the methods of the handle, and events are received with [`VideoStream::recv_event`] and
+/// its siblings.
+///
+/// Dropping the handle closes the stream: the pipeline is moved to the `Null` state and all the
+/// threads of the stream are joined before `drop` returns.
+///
+/// # Example
+///
+/// ```no_run
+/// struct VideoFrameLoader;
+///
+/// impl dextreamer::FrameHandler for VideoFrameLoader {
+///     fn handle_new_frame(&self, frame: &dextreamer::VideoFrameRef<'_>) {
+///         println!("New frame: {}x{}", frame.width(), frame.height());
+///     }
+/// }
+///
+/// let video_stream = dextreamer::open_video("file:///home/user/my_video.mkv", VideoFrameLoader)
+///     .expect("to open the video");
+///
+/// while let Some(event) = video_stream.recv_event() {
+///     if let dextreamer::VideoStreamEvent::VideoLoaded(info) = event {
+///         println!("Video loaded: {:?}", info);
+///         break;
+///     }
+/// }
+///
+/// video_stream.seek(30.0).expect("the stream to be open");
+/// video_stream.close();
+/// ```
+pub struct VideoStream {
+    playbin_pipeline: gst::Element,
+    internal_sender: Sender<InternalMessage>,
+    event_receiver: Arc<EventReceiver>,
+    mailbox: Option<FrameMailbox>,
+    frame_receiver: Option<FrameReceiver>,
+    sink_handle: SinkHandle,
+    control_thread_handle: Option<JoinHandle<()>>,
+}
+
+impl VideoStream {
+    pub(crate) fn new(
+        playbin_pipeline: gst::Element,
+        internal_sender: Sender<InternalMessage>,
+        event_receiver: EventReceiver,
+        mailbox: Option<FrameMailbox>,
+        frame_receiver: Option<FrameReceiver>,
+        sink_handle: SinkHandle,
+        control_thread_handle: JoinHandle<()>,
+    ) -> Self {
+        Self {
+            playbin_pipeline,
+            internal_sender,
+            event_receiver: Arc::new(event_receiver),
+            mailbox,
+            frame_receiver,
+            sink_handle,
+            control_thread_handle: Some(control_thread_handle),
+        }
+    }
+
+    /// Sends an action to the video thread.
+    ///
+    /// Returns `DextreamerError::ChannelDisconnected` if the stream has already been closed.
+    pub fn send(&self, action: VideoStreamAction) -> Result<(), DextreamerError> {
+        self.internal_sender
+            .send(InternalMessage::VideoStreamAction(action))
+            .map_err(|_| DextreamerError::ChannelDisconnected)
+    }
+
+    /// Starts or resumes playback.
+    pub fn play(&self) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetPlay)
+    }
+
+    /// Pauses playback.
+    pub fn pause(&self) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetPause)
+    }
+
+    /// Seeks to a specific position in the video, provided in seconds.
+    pub fn seek(&self, seconds: f64) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SeekToSeconds(seconds))
+    }
+
+    /// Seeks to `target`, trading precision for speed according to `mode`.
+    pub fn seek_to(&self, target: SeekTarget, mode: SeekMode) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::Seek { target, mode })
+    }
+
+    /// Sets the playback rate, 1.0 being the normal speed. Negative rates play the video
+    /// backwards.
+    pub fn set_rate(&self, rate: f64) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetRate(rate))
+    }
+
+    /// Pauses the stream and moves by `frames` frames, backwards if negative.
+    pub fn step_frames(&self, frames: i32) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::StepFrames(frames))
+    }
+
+    /// Plays the whole media or a part of it over and over, or stops looping.
+    pub fn set_loop(&self, loop_mode: LoopMode) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetLoop(loop_mode))
+    }
+
+    /// Sets the volume. The volume should be between 0.0 and 1.0.
+    pub fn set_volume(&self, volume: f32) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetVolume(volume))
+    }
+
+    /// Changes the size of the frames, e.g. when the window displaying them is resized.
+    pub fn set_output_size(&self, output_size: OutputSize) -> Result<(), DextreamerError> {
+        self.send(VideoStreamAction::SetOutputSize(output_size))
+    }
+
+    /// Blocks until the next event is emitted. Returns `None` once the stream is closed and all
+    /// the events have been received.
+    pub fn recv_event(&self) -> Option<VideoStreamEvent> {
+        self.event_receiver.recv()
+    }
+
+    /// Returns the next event if there is one, without blocking.
+    pub fn try_recv_event(&self) -> Option<VideoStreamEvent> {
+        self.event_receiver.try_recv()
+    }
+
+    /// Waits for the next event for at most `timeout`.
+    pub fn recv_event_timeout(&self, timeout: Duration) -> Option<VideoStreamEvent> {
+        self.event_receiver.recv_timeout(timeout)
+    }
+
+    /// Takes the mailbox holding the newest frame, so that it can be polled from another thread,
+    /// e.g. a render thread, while events are received on this one.
+    ///
+    /// Returns `None` if the mailbox hasn't been enabled with `VideoStreamBuilder::frame_mailbox`,
+    /// or if it has already been taken.
+    pub fn take_frame_mailbox(&mut self) -> Option<FrameMailbox> {
+        self.mailbox.take()
+    }
+
+    /// Takes the receiving end of the frame queue, so that the frames can be received on another
+    /// thread.
+    ///
+    /// Returns `None` if the queue hasn't been enabled with `VideoStreamBuilder::frame_queue`, or
+    /// if the receiver has already been taken.
+    pub fn take_frame_receiver(&mut self) -> Option<FrameReceiver> {
+        self.frame_receiver.take()
+    }
+
+    /// Adds a frame handler to the live stream, next to the one set with the
+    /// `VideoStreamBuilder`. The handler receives the frames in the format and size selected with
+    /// `options`, starting with the next decoded frame.
+    ///
+    /// Every added handler runs on a thread of its own: a slow handler drops its own frames
+    /// rather than stalling the playback. Returns `DextreamerError::FrameBranch` if the branch of
+    /// the handler can't be linked into the pipeline, in which case the stream is left unchanged.
+    pub fn add_frame_handler(
+        &self,
+        frame_handler: impl FrameHandler + 'static,
+        options: HandlerOptions,
+    ) -> Result<HandlerId, DextreamerError> {
+        self.sink_handle
+            .branches
+            .add(Box::new(Infallible(frame_handler)), options)
+    }
+
+    /// Adds a `FrameHandlerMut` to the live stream. See [`VideoStream::add_frame_handler`].
+    pub fn add_frame_handler_mut(
+        &self,
+        frame_handler: impl FrameHandlerMut + 'static,
+        options: HandlerOptions,
+    ) -> Result<HandlerId, DextreamerError> {
+        self.sink_handle
+            .branches
+            .add(Box::new(Fallible(frame_handler)), options)
+    }
+
+    /// Removes a frame handler added with [`VideoStream::add_frame_handler`]. The handler is
+    /// dropped once the frame it may be handling is done.
+    pub fn remove_frame_handler(&self, handler_id: HandlerId) -> Result<(), DextreamerError> {
+        self.sink_handle.branches.remove(handler_id)
+    }
+
+    /// Returns the frame counters of the stream.
+    pub fn statistics(&self) -> StreamStatistics {
+        self.sink_handle.statistics.snapshot()
+    }
+
+    /// Closes the stream and waits for all of its threads to finish.
+    pub fn close(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let Some(control_thread_handle) = self.control_thread_handle.take() else {
+            return;
+        };
+
+        // Fails if the stream has already been closed with `VideoStreamAction::Close`
+        let _ = self.send(VideoStreamAction::Close);
+        // The control thread only panics if there is a bug in this crate, in which case it has already been reported
+        let _ = control_thread_handle.join();
+
+        // The control thread moves the pipeline to `Null` on exit, this is only a safety net
+        //  in case it has panicked.
+        let _ = self.playbin_pipeline.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(feature = "async")]
+impl VideoStream {
+    /// Returns a `Stream` of the events of the stream. See [`VideoStream::recv_event`].
+    pub fn events(&self) -> EventStream {
+        EventStream::new(self.event_receiver.clone())
+    }
+
+    /// Starts or resumes playback. The returned future resolves once the pipeline is playing.
+    pub fn play_async(&self) -> Completion {
+        self.await_playing_state(PlayingState::Playing)
+    }
+
+    /// Pauses playback. The returned future resolves once the pipeline is paused.
+    pub fn pause_async(&self) -> Completion {
+        self.await_playing_state(PlayingState::Paused)
+    }
+
+    fn await_playing_state(&self, playing_state: PlayingState) -> Completion {
+        let (completion_sender, completion) = completion();
+
+        let message = InternalMessage::AwaitPlayingState(playing_state, completion_sender);
+        // The completion resolves with `DextreamerError::ChannelDisconnected` once the message,
+        //  along with the completion sender, is dropped
+        let _ = self.internal_sender.send(message);
+
+        completion
+    }
+}
+
+impl Drop for VideoStream {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}